@@ -1,6 +1,12 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::bus::{ArrivalBoard, ArrivalEvent, ArrivalWatcher, WatchOptions};
 use crate::models::bus::prelude::*;
 use crate::{Client, LTAResult};
 use concat_string::concat_string;
+use futures_util::stream::{self, Stream, StreamExt};
+use time::OffsetDateTime;
 
 use super::ClientExt;
 
@@ -73,4 +79,72 @@ pub trait BusRequests<C: Client + ClientExt> {
             )
             .await
     }
+
+    /// Requests the arrivals of `bus_stop_codes` with up to `concurrency` requests at a time
+    /// and merges them into one board, soonest first. `service_no` applies to every stop.
+    /// Stops that fail are listed in [`ArrivalBoard::failed`] instead of failing the board
+    ///
+    /// **Update freq**: 1min
+    async fn get_arrival_board<'a, S>(
+        client: &C,
+        bus_stop_codes: &[u32],
+        service_no: S,
+        concurrency: usize,
+    ) -> ArrivalBoard
+    where
+        S: Into<Option<&'a str>>,
+    {
+        let service_no = service_no.into();
+        let responses = stream::iter(bus_stop_codes.iter().copied())
+            .map(|code| async move { (code, Self::get_arrival(client, code, service_no).await) })
+            .buffered(concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        ArrivalBoard::new(responses)
+    }
+
+    /// Requests the arrivals at `bus_stop_code` every [`WatchOptions::interval`] and yields
+    /// what changed between responses. Failed requests are yielded as errors and the stream
+    /// carries on with the next request
+    fn watch_arrival<'a, S>(
+        client: &'a C,
+        bus_stop_code: u32,
+        service_no: S,
+        options: WatchOptions,
+    ) -> impl Stream<Item = LTAResult<ArrivalEvent>> + 'a
+    where
+        S: Into<Option<&'a str>>,
+    {
+        let service_no = service_no.into();
+        let state = (
+            ArrivalWatcher::new(options),
+            VecDeque::new(),
+            None::<Instant>,
+        );
+
+        stream::unfold(
+            state,
+            move |(mut watcher, mut pending, mut last)| async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((Ok(event), (watcher, pending, last)));
+                    }
+
+                    if let Some(last) = last {
+                        let wait = watcher.options().interval.saturating_sub(last.elapsed());
+                        futures_timer::Delay::new(wait).await;
+                    }
+                    last = Some(Instant::now());
+
+                    match Self::get_arrival(client, bus_stop_code, service_no).await {
+                        Ok(resp) => {
+                            pending.extend(watcher.update(&resp, OffsetDateTime::now_utc()))
+                        }
+                        Err(e) => return Some((Err(e), (watcher, pending, last))),
+                    }
+                }
+            },
+        )
+    }
 }
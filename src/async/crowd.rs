@@ -1,9 +1,11 @@
+use crate::crowd::passenger_vol::{available_months, YearMonth};
+use crate::link::PresignedLink;
 use crate::models::crowd::crowd_density::CrowdDensityForecast;
 use crate::models::crowd::passenger_vol;
 use crate::models::crowd::prelude::*;
+use crate::utils::today_sgt;
 use crate::{Client, LTAResult};
 use lta_models::prelude::MrtLine;
-use time::Date;
 
 use super::ClientExt;
 
@@ -12,16 +14,25 @@ pub trait CrowdRequests<C: Client + ClientExt> {
     /// **Update freq**: By 15th of every month, the passenger volume for previous month data
     /// will be generated
     ///
-    /// Note: Link will expire after 5mins!
+    /// Only the months returned by [`CrowdRequests::passenger_vol_months`] can be requested,
+    /// other months return `LTAError::UnavailableMonth`. If `None` is provided, the latest
+    /// month is returned.
+    ///
+    /// Note: Link will expire after 5mins, see [`PresignedLink`]
     async fn get_passenger_vol_by<S, D>(
         client: &C,
         vol_type: passenger_vol::VolType,
         date: D,
         skip: S,
-    ) -> LTAResult<Vec<String>>
+    ) -> LTAResult<Vec<PresignedLink>>
     where
         S: Into<Option<u32>>,
-        D: Into<Option<Date>>;
+        D: Into<Option<YearMonth>>;
+
+    /// Returns the months that passenger volume data is currently available for, latest first
+    fn passenger_vol_months() -> Vec<YearMonth> {
+        available_months(today_sgt())
+    }
 
     /// Returns real-time platform crowdedness level for the MRT/LRT stations of a
     /// particular train network line
@@ -1,4 +1,7 @@
+use crate::facility::FacilityMaintenance;
+use crate::link::PresignedLink;
 use crate::models::train::prelude::*;
+use crate::train::station_codes;
 use crate::{Client, LTAResult};
 
 use super::ClientExt;
@@ -10,5 +13,40 @@ pub trait FacilityRequests<C: Client + ClientExt> {
     async fn get_facilities_maintenance(
         client: &C,
         station_code: StationCode,
-    ) -> LTAResult<Vec<String>>;
+    ) -> LTAResult<Vec<PresignedLink>>;
+
+    /// Downloads and decodes the facilities maintenance schedules of the particular station
+    ///
+    /// **Update Freq**: Adhoc
+    async fn get_facilities_maintenance_records(
+        client: &C,
+        station_code: StationCode,
+    ) -> LTAResult<Vec<FacilityMaintenance>> {
+        let links = Self::get_facilities_maintenance(client, station_code).await?;
+        let mut records = Vec::new();
+
+        for link in links {
+            let bytes = client.download(link.url()).await?;
+            records.extend(FacilityMaintenance::from_json(&bytes)?);
+        }
+
+        Ok(records)
+    }
+
+    /// Downloads and decodes the facilities maintenance schedules of every station on `line`.
+    /// Stations are requested one after another
+    ///
+    /// **Update Freq**: Adhoc
+    async fn get_facilities_maintenance_by_line(
+        client: &C,
+        line: MrtLine,
+    ) -> LTAResult<Vec<FacilityMaintenance>> {
+        let mut records = Vec::new();
+
+        for station_code in station_codes(&line) {
+            records.extend(Self::get_facilities_maintenance_records(client, station_code).await?);
+        }
+
+        Ok(records)
+    }
 }
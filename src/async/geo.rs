@@ -1,7 +1,11 @@
 use super::ClientExt;
+use crate::link::PresignedLink;
 use crate::models::geo::prelude::*;
 use crate::{Client, LTAResult};
 
+#[cfg(feature = "shapefile")]
+use crate::geo::GeospatialLayer;
+
 pub trait GeoRequests<C: Client + ClientExt> {
     /// Returns the SHP files of the requested geospatial layer
     ///
@@ -9,5 +13,25 @@ pub trait GeoRequests<C: Client + ClientExt> {
     async fn get_geospatial_whole_island(
         client: &C,
         id: GeospatialLayerId,
-    ) -> LTAResult<Vec<String>>;
+    ) -> LTAResult<Vec<PresignedLink>>;
+
+    /// Downloads and decodes the SHP files of the requested geospatial layer.
+    /// Coordinates are in SVY21, use `GeospatialLayer::to_wgs84` to reproject them
+    ///
+    /// **Update Freq**: Adhoc
+    #[cfg(feature = "shapefile")]
+    async fn get_geospatial_layers(
+        client: &C,
+        id: GeospatialLayerId,
+    ) -> LTAResult<Vec<GeospatialLayer>> {
+        let links = Self::get_geospatial_whole_island(client, id).await?;
+        let mut layers = Vec::with_capacity(links.len());
+
+        for link in links {
+            let bytes = client.download(link.url()).await?;
+            layers.extend(GeospatialLayer::from_zip(&bytes)?);
+        }
+
+        Ok(layers)
+    }
 }
@@ -0,0 +1,64 @@
+use http::StatusCode;
+
+use crate::link::{LinkSource, PresignedLink};
+use crate::{
+    Client, Crowd, CrowdRequests, Facility, FacilityRequests, Geo, GeoRequests, LTAError,
+    LTAResult, Traffic, TrafficRequests,
+};
+
+use super::ClientExt;
+
+/// Downloading and refreshing of [`PresignedLink`]s through the client that returned them
+pub trait PresignedLinkExt<C: Client + ClientExt> {
+    /// Requests the link again from the API that returned it
+    async fn refresh(&self, client: &C) -> LTAResult<PresignedLink>;
+
+    /// Downloads the contents of the link. If the link has expired, or the download is
+    /// rejected as expired, a fresh link is requested and replaces `self` first
+    async fn download(&mut self, client: &C) -> LTAResult<Vec<u8>>;
+}
+
+impl<C> PresignedLinkExt<C> for PresignedLink
+where
+    C: Client + ClientExt,
+    Crowd: CrowdRequests<C>,
+    Geo: GeoRequests<C>,
+    Facility: FacilityRequests<C>,
+    Traffic: TrafficRequests<C>,
+{
+    async fn refresh(&self, client: &C) -> LTAResult<PresignedLink> {
+        let links = match self.source().clone() {
+            LinkSource::PassengerVol {
+                vol_type,
+                month,
+                skip,
+            } => Crowd::get_passenger_vol_by(client, vol_type, month, skip).await?,
+            LinkSource::GeospatialWholeIsland(id) => {
+                Geo::get_geospatial_whole_island(client, id).await?
+            }
+            LinkSource::FacilitiesMaintenance(station_code) => {
+                Facility::get_facilities_maintenance(client, station_code).await?
+            }
+            LinkSource::TrafficFlow => Traffic::get_traffic_flow(client).await?,
+        };
+
+        links
+            .into_iter()
+            .nth(self.index())
+            .ok_or(LTAError::LinkUnavailable)
+    }
+
+    async fn download(&mut self, client: &C) -> LTAResult<Vec<u8>> {
+        if self.is_expired() {
+            *self = self.refresh(client).await?;
+        }
+
+        match client.download(self.url()).await {
+            Err(LTAError::UnhandledStatusCode(StatusCode::FORBIDDEN, _)) => {
+                *self = self.refresh(client).await?;
+                client.download(self.url()).await
+            }
+            res => res,
+        }
+    }
+}
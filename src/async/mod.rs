@@ -3,6 +3,7 @@ pub mod client;
 pub mod crowd;
 pub mod facility;
 pub mod geo;
+pub mod link;
 pub mod taxi;
 pub mod traffic;
 pub mod train;
@@ -14,7 +15,7 @@ pub use crate::r#async::client::LTAClient;
 pub mod prelude {
     pub use crate::r#async::{
         bus::BusRequests, crowd::CrowdRequests, facility::FacilityRequests, geo::GeoRequests,
-        taxi::TaxiRequests, traffic::TrafficRequests, train::TrainRequests,
+        link::PresignedLinkExt, taxi::TaxiRequests, traffic::TrafficRequests, train::TrainRequests,
     };
 }
 
@@ -27,4 +28,8 @@ pub trait ClientExt: Client {
     where
         F: FnOnce(Self::RB) -> Self::RB,
         for<'de> T: serde::Deserialize<'de> + Into<T2>;
+
+    /// Downloads the body of `url` as is, without the `AccountKey` header.
+    /// Used for the pre-signed links returned by some of the APIs
+    async fn download(&self, url: &str) -> LTAResult<Vec<u8>>;
 }
@@ -1,6 +1,17 @@
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use crate::link::{LinkSource, PresignedLink};
 use crate::models::traffic::prelude::*;
+use crate::traffic::images::select_cameras;
+use crate::traffic::{
+    ArchiveReport, AvailabilityRecorder, CameraImage, ImageArchiver, RecordReport,
+    TrafficFlowDataset, CARPARK_PAGE_SIZE,
+};
 use crate::{Client, LTAError, LTAResult};
 use concat_string::concat_string;
+use futures_util::stream::{self, StreamExt};
+use time::OffsetDateTime;
 
 use super::ClientExt;
 
@@ -176,12 +187,137 @@ pub trait TrafficRequests<C: Client + ClientExt> {
     /// every quarter during 0700-0900 hours.
     ///
     /// **Update freq**: Quaterly
-    async fn get_traffic_flow(client: &C) -> LTAResult<Vec<String>> {
+    async fn get_traffic_flow(client: &C) -> LTAResult<Vec<PresignedLink>> {
         client
             .build_req_with_skip::<TrafficFlowRawResp, _>(
                 &concat_string!(client.base_url(), "/TrafficFlow"),
                 None,
             )
             .await
+            .map(|urls| PresignedLink::from_urls(urls, LinkSource::TrafficFlow))
+    }
+
+    /// Downloads and decodes the hourly average traffic flow of every road link
+    ///
+    /// **Update freq**: Quaterly
+    async fn get_traffic_flow_dataset(client: &C) -> LTAResult<TrafficFlowDataset> {
+        let links = Self::get_traffic_flow(client).await?;
+        let mut dataset = TrafficFlowDataset::default();
+
+        for link in links {
+            let bytes = client.download(link.url()).await?;
+            dataset.extend(TrafficFlowDataset::from_json(&bytes)?);
+        }
+
+        Ok(dataset)
+    }
+
+    /// Downloads the images of `camera_ids`, or of every camera if `camera_ids` is empty,
+    /// with up to `concurrency` downloads at a time. Failed downloads are returned per camera
+    ///
+    /// **Update freq**: 1 to 5 minutes
+    async fn download_traffic_images(
+        client: &C,
+        camera_ids: &[u32],
+        concurrency: usize,
+    ) -> LTAResult<Vec<(u32, LTAResult<CameraImage>)>> {
+        let images = select_cameras(
+            Self::get_traffic_images(client, None::<u32>).await?,
+            camera_ids,
+        );
+        let downloads = stream::iter(images)
+            .map(|image| async move {
+                let res = client
+                    .download(&image.image_link)
+                    .await
+                    .map(|bytes| CameraImage::new(&image, bytes));
+                (image.camera_id, res)
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+
+        Ok(downloads)
+    }
+
+    /// Downloads the images of the cameras of `archiver` and stores the ones that have changed
+    async fn archive_traffic_images(
+        client: &C,
+        archiver: &mut ImageArchiver,
+    ) -> LTAResult<ArchiveReport> {
+        let images = Self::download_traffic_images(
+            client,
+            archiver.camera_ids(),
+            archiver.max_concurrency(),
+        )
+        .await?;
+        Ok(archiver.store(images))
+    }
+
+    /// Runs [`TrafficRequests::archive_traffic_images`] every `interval` until `on_round`
+    /// returns `ControlFlow::Break`. Errors of a round are passed to `on_round` rather than
+    /// stopping the archiver
+    async fn archive_traffic_images_every<F>(
+        client: &C,
+        archiver: &mut ImageArchiver,
+        interval: Duration,
+        mut on_round: F,
+    ) where
+        F: FnMut(LTAResult<ArchiveReport>) -> ControlFlow<()>,
+    {
+        loop {
+            let started = Instant::now();
+            let report = Self::archive_traffic_images(client, archiver).await;
+            if on_round(report).is_break() {
+                return;
+            }
+            futures_timer::Delay::new(interval.saturating_sub(started.elapsed())).await;
+        }
+    }
+
+    /// Returns every carpark, requesting pages of [`CARPARK_PAGE_SIZE`] until the last one
+    ///
+    /// **Update freq**: 1 min
+    async fn get_all_carpark_avail(client: &C) -> LTAResult<Vec<CarPark>> {
+        let mut carparks = Vec::new();
+        loop {
+            let page = Self::get_carpark_avail(client, carparks.len() as u32).await?;
+            let is_last = page.len() < CARPARK_PAGE_SIZE as usize;
+            carparks.extend(page);
+            if is_last {
+                return Ok(carparks);
+            }
+        }
+    }
+
+    /// Requests every carpark and appends their availability to `recorder`
+    async fn record_carpark_avail(
+        client: &C,
+        recorder: &mut AvailabilityRecorder,
+    ) -> LTAResult<RecordReport> {
+        let at = OffsetDateTime::now_utc();
+        let carparks = Self::get_all_carpark_avail(client).await?;
+        recorder.record(&carparks, at)
+    }
+
+    /// Runs [`TrafficRequests::record_carpark_avail`] every `interval` until `on_round`
+    /// returns `ControlFlow::Break`. Errors of a round are passed to `on_round` rather than
+    /// stopping the recorder
+    async fn record_carpark_avail_every<F>(
+        client: &C,
+        recorder: &mut AvailabilityRecorder,
+        interval: Duration,
+        mut on_round: F,
+    ) where
+        F: FnMut(LTAResult<RecordReport>) -> ControlFlow<()>,
+    {
+        loop {
+            let started = Instant::now();
+            let report = Self::record_carpark_avail(client, recorder).await;
+            if on_round(report).is_break() {
+                return;
+            }
+            futures_timer::Delay::new(interval.saturating_sub(started.elapsed())).await;
+        }
     }
 }
@@ -1,8 +1,14 @@
-use crate::blocking::ClientExt;
+use std::collections::VecDeque;
+use std::thread;
+use std::time::Instant;
+
+use crate::blocking::{map_concurrent, ClientExt};
+use crate::bus::{ArrivalBoard, ArrivalEvent, ArrivalWatcher, WatchOptions};
 use crate::models::bus::prelude::*;
 use crate::Client;
 use crate::LTAResult;
 use concat_string::concat_string;
+use time::OffsetDateTime;
 
 /// All API pertaining to buses
 pub trait BusRequests<C: Client + ClientExt> {
@@ -56,4 +62,57 @@ pub trait BusRequests<C: Client + ClientExt> {
             skip.into(),
         )
     }
+
+    /// Requests the arrivals of `bus_stop_codes` on up to `concurrency` threads and merges
+    /// them into one board, soonest first. `service_no` applies to every stop. Stops that
+    /// fail are listed in [`ArrivalBoard::failed`] instead of failing the board
+    ///
+    /// **Update freq**: 1min
+    fn get_arrival_board<'a>(
+        client: &C,
+        bus_stop_codes: &[u32],
+        service_no: impl Into<Option<&'a str>>,
+        concurrency: usize,
+    ) -> ArrivalBoard
+    where
+        C: Sync,
+    {
+        let service_no = service_no.into();
+        let responses = map_concurrent(bus_stop_codes.to_vec(), concurrency, |code| {
+            (code, Self::get_arrival(client, code, service_no))
+        });
+
+        ArrivalBoard::new(responses)
+    }
+
+    /// Requests the arrivals at `bus_stop_code` every [`WatchOptions::interval`] and yields
+    /// what changed between responses, blocking until there is a change. Failed requests
+    /// are yielded as errors and the iterator carries on with the next request
+    fn watch_arrival<'a>(
+        client: &'a C,
+        bus_stop_code: u32,
+        service_no: impl Into<Option<&'a str>>,
+        options: WatchOptions,
+    ) -> impl Iterator<Item = LTAResult<ArrivalEvent>> + 'a {
+        let service_no = service_no.into();
+        let mut watcher = ArrivalWatcher::new(options);
+        let mut pending = VecDeque::new();
+        let mut last = None::<Instant>;
+
+        std::iter::from_fn(move || loop {
+            if let Some(event) = pending.pop_front() {
+                return Some(Ok(event));
+            }
+
+            if let Some(last) = last {
+                thread::sleep(watcher.options().interval.saturating_sub(last.elapsed()));
+            }
+            last = Some(Instant::now());
+
+            match Self::get_arrival(client, bus_stop_code, service_no) {
+                Ok(resp) => pending.extend(watcher.update(&resp, OffsetDateTime::now_utc())),
+                Err(e) => return Some(Err(e)),
+            }
+        })
+    }
 }
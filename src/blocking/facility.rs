@@ -1,4 +1,7 @@
-use crate::models::train::StationCode;
+use crate::facility::FacilityMaintenance;
+use crate::link::PresignedLink;
+use crate::models::train::prelude::*;
+use crate::train::station_codes;
 use crate::{Client, LTAResult};
 
 use super::ClientExt;
@@ -7,5 +10,46 @@ pub trait FacilityRequests<C: Client + ClientExt> {
     /// Returns pre-signed links to JSON file containing facilities maintenance schedules of the particular station
     ///
     /// **Update Freq**: Adhoc
-    fn get_facilities_maintenance(client: &C, station_code: StationCode) -> LTAResult<Vec<String>>;
-}
\ No newline at end of file
+    fn get_facilities_maintenance(
+        client: &C,
+        station_code: StationCode,
+    ) -> LTAResult<Vec<PresignedLink>>;
+
+    /// Downloads and decodes the facilities maintenance schedules of the particular station
+    ///
+    /// **Update Freq**: Adhoc
+    fn get_facilities_maintenance_records(
+        client: &C,
+        station_code: StationCode,
+    ) -> LTAResult<Vec<FacilityMaintenance>> {
+        let links = Self::get_facilities_maintenance(client, station_code)?;
+        let mut records = Vec::new();
+
+        for link in links {
+            let bytes = client.download(link.url())?;
+            records.extend(FacilityMaintenance::from_json(&bytes)?);
+        }
+
+        Ok(records)
+    }
+
+    /// Downloads and decodes the facilities maintenance schedules of every station on `line`.
+    /// Stations are requested one after another
+    ///
+    /// **Update Freq**: Adhoc
+    fn get_facilities_maintenance_by_line(
+        client: &C,
+        line: MrtLine,
+    ) -> LTAResult<Vec<FacilityMaintenance>> {
+        let mut records = Vec::new();
+
+        for station_code in station_codes(&line) {
+            records.extend(Self::get_facilities_maintenance_records(
+                client,
+                station_code,
+            )?);
+        }
+
+        Ok(records)
+    }
+}
@@ -1,14 +1,35 @@
-use crate::models::geo::geospatial_whole_island::{
-    GeospatialLayerId,
-};
+use crate::link::PresignedLink;
+use crate::models::geo::geospatial_whole_island::GeospatialLayerId;
 use crate::{Client, LTAResult};
 
+#[cfg(feature = "shapefile")]
+use crate::geo::GeospatialLayer;
+
 use super::ClientExt;
 
 pub trait GeoRequests<C: Client + ClientExt> {
     /// Returns the SHP files of the requested geospatial layer
     ///
     /// **Update Freq**: Adhoc
-    fn get_geospatial_whole_island(client: &C, id: GeospatialLayerId) -> LTAResult<Vec<String>>;
-}
+    fn get_geospatial_whole_island(
+        client: &C,
+        id: GeospatialLayerId,
+    ) -> LTAResult<Vec<PresignedLink>>;
 
+    /// Downloads and decodes the SHP files of the requested geospatial layer.
+    /// Coordinates are in SVY21, use `GeospatialLayer::to_wgs84` to reproject them
+    ///
+    /// **Update Freq**: Adhoc
+    #[cfg(feature = "shapefile")]
+    fn get_geospatial_layers(client: &C, id: GeospatialLayerId) -> LTAResult<Vec<GeospatialLayer>> {
+        let links = Self::get_geospatial_whole_island(client, id)?;
+        let mut layers = Vec::with_capacity(links.len());
+
+        for link in links {
+            let bytes = client.download(link.url())?;
+            layers.extend(GeospatialLayer::from_zip(&bytes)?);
+        }
+
+        Ok(layers)
+    }
+}
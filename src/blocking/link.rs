@@ -0,0 +1,60 @@
+use http::StatusCode;
+
+use crate::blocking::prelude::*;
+use crate::link::{LinkSource, PresignedLink};
+use crate::{Client, Crowd, Facility, Geo, LTAError, LTAResult, Traffic};
+
+use super::ClientExt;
+
+/// Downloading and refreshing of [`PresignedLink`]s through the client that returned them
+pub trait PresignedLinkExt<C: Client + ClientExt> {
+    /// Requests the link again from the API that returned it
+    fn refresh(&self, client: &C) -> LTAResult<PresignedLink>;
+
+    /// Downloads the contents of the link. If the link has expired, or the download is
+    /// rejected as expired, a fresh link is requested and replaces `self` first
+    fn download(&mut self, client: &C) -> LTAResult<Vec<u8>>;
+}
+
+impl<C> PresignedLinkExt<C> for PresignedLink
+where
+    C: Client + ClientExt,
+    Crowd: CrowdRequests<C>,
+    Geo: GeoRequests<C>,
+    Facility: FacilityRequests<C>,
+    Traffic: TrafficRequests<C>,
+{
+    fn refresh(&self, client: &C) -> LTAResult<PresignedLink> {
+        let links = match self.source().clone() {
+            LinkSource::PassengerVol {
+                vol_type,
+                month,
+                skip,
+            } => Crowd::get_passenger_vol_by(client, vol_type, month, skip)?,
+            LinkSource::GeospatialWholeIsland(id) => Geo::get_geospatial_whole_island(client, id)?,
+            LinkSource::FacilitiesMaintenance(station_code) => {
+                Facility::get_facilities_maintenance(client, station_code)?
+            }
+            LinkSource::TrafficFlow => Traffic::get_traffic_flow(client)?,
+        };
+
+        links
+            .into_iter()
+            .nth(self.index())
+            .ok_or(LTAError::LinkUnavailable)
+    }
+
+    fn download(&mut self, client: &C) -> LTAResult<Vec<u8>> {
+        if self.is_expired() {
+            *self = self.refresh(client)?;
+        }
+
+        match client.download(self.url()) {
+            Err(LTAError::UnhandledStatusCode(StatusCode::FORBIDDEN, _)) => {
+                *self = self.refresh(client)?;
+                client.download(self.url())
+            }
+            res => res,
+        }
+    }
+}
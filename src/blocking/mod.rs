@@ -3,10 +3,14 @@ pub mod client;
 pub mod crowd;
 pub mod facility;
 pub mod geo;
+pub mod link;
 pub mod taxi;
 pub mod traffic;
 pub mod train;
 
+use std::sync::Mutex;
+use std::thread;
+
 use crate::{Client, LTAResult};
 
 pub use client::LTAClient;
@@ -14,7 +18,7 @@ pub use client::LTAClient;
 pub mod prelude {
     pub use crate::blocking::{
         bus::BusRequests, crowd::CrowdRequests, facility::FacilityRequests, geo::GeoRequests,
-        taxi::TaxiRequests, traffic::TrafficRequests, train::TrainRequests,
+        link::PresignedLinkExt, taxi::TaxiRequests, traffic::TrafficRequests, train::TrainRequests,
     };
 }
 
@@ -27,4 +31,50 @@ pub trait ClientExt: Client {
     where
         F: FnOnce(Self::RB) -> Self::RB,
         for<'de> T: serde::Deserialize<'de> + Into<T2>;
-}
\ No newline at end of file
+
+    /// Downloads the body of `url` as is, without the `AccountKey` header.
+    /// Used for the pre-signed links returned by some of the APIs
+    fn download(&self, url: &str) -> LTAResult<Vec<u8>>;
+}
+
+/// Maps `items` with `f` on up to `limit` threads, keeping the order of `items`
+pub(crate) fn map_concurrent<T, R, F>(items: Vec<T>, limit: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let len = items.len();
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new((0..len).map(|_| None).collect::<Vec<Option<R>>>());
+
+    thread::scope(|s| {
+        for _ in 0..limit.clamp(1, len.max(1)) {
+            s.spawn(|| loop {
+                let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next();
+                let Some((i, item)) = next else { break };
+                let res = f(item);
+                results.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(res);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::map_concurrent;
+
+    #[test]
+    fn map_concurrent_keeps_order() {
+        let squares = map_concurrent((0..50).collect(), 4, |n: u32| n * n);
+        assert_eq!(squares, (0..50).map(|n| n * n).collect::<Vec<_>>());
+        assert!(map_concurrent(Vec::<u32>::new(), 4, |n| n).is_empty());
+    }
+}
@@ -1,8 +1,18 @@
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use crate::link::{LinkSource, PresignedLink};
 use crate::models::traffic::prelude::*;
+use crate::traffic::images::select_cameras;
+use crate::traffic::{
+    ArchiveReport, AvailabilityRecorder, CameraImage, ImageArchiver, RecordReport,
+    TrafficFlowDataset, CARPARK_PAGE_SIZE,
+};
 use crate::{Client, LTAError, LTAResult};
 use concat_string::concat_string;
+use time::OffsetDateTime;
 
-use super::ClientExt;
+use super::{map_concurrent, ClientExt};
 
 pub trait TrafficRequests<C: Client + ClientExt> {
     /// Returns ERP rates of all vehicle types across all timings for each
@@ -141,10 +151,131 @@ pub trait TrafficRequests<C: Client + ClientExt> {
     /// every quarter during 0700-0900 hours.
     ///
     /// **Update freq**: Quaterly
-    fn get_traffic_flow(client: &C) -> LTAResult<Vec<String>> {
-        client.build_req_with_skip::<TrafficFlowRawResp, _>(
-            &concat_string!(client.base_url(), "/TrafficFlow"),
-            None,
-        )
+    fn get_traffic_flow(client: &C) -> LTAResult<Vec<PresignedLink>> {
+        client
+            .build_req_with_skip::<TrafficFlowRawResp, _>(
+                &concat_string!(client.base_url(), "/TrafficFlow"),
+                None,
+            )
+            .map(|urls| PresignedLink::from_urls(urls, LinkSource::TrafficFlow))
+    }
+
+    /// Downloads and decodes the hourly average traffic flow of every road link
+    ///
+    /// **Update freq**: Quaterly
+    fn get_traffic_flow_dataset(client: &C) -> LTAResult<TrafficFlowDataset> {
+        let links = Self::get_traffic_flow(client)?;
+        let mut dataset = TrafficFlowDataset::default();
+
+        for link in links {
+            let bytes = client.download(link.url())?;
+            dataset.extend(TrafficFlowDataset::from_json(&bytes)?);
+        }
+
+        Ok(dataset)
+    }
+
+    /// Downloads the images of `camera_ids`, or of every camera if `camera_ids` is empty,
+    /// with up to `concurrency` downloads at a time. Failed downloads are returned per camera
+    ///
+    /// **Update freq**: 1 to 5 minutes
+    fn download_traffic_images(
+        client: &C,
+        camera_ids: &[u32],
+        concurrency: usize,
+    ) -> LTAResult<Vec<(u32, LTAResult<CameraImage>)>>
+    where
+        C: Sync,
+    {
+        let images = select_cameras(Self::get_traffic_images(client, None::<u32>)?, camera_ids);
+        let downloads = map_concurrent(images, concurrency, |image| {
+            let res = client
+                .download(&image.image_link)
+                .map(|bytes| CameraImage::new(&image, bytes));
+            (image.camera_id, res)
+        });
+
+        Ok(downloads)
+    }
+
+    /// Downloads the images of the cameras of `archiver` and stores the ones that have changed
+    fn archive_traffic_images(client: &C, archiver: &mut ImageArchiver) -> LTAResult<ArchiveReport>
+    where
+        C: Sync,
+    {
+        let images = Self::download_traffic_images(
+            client,
+            archiver.camera_ids(),
+            archiver.max_concurrency(),
+        )?;
+        Ok(archiver.store(images))
+    }
+
+    /// Runs [`TrafficRequests::archive_traffic_images`] every `interval` until `on_round`
+    /// returns `ControlFlow::Break`. Errors of a round are passed to `on_round` rather than
+    /// stopping the archiver
+    fn archive_traffic_images_every<F>(
+        client: &C,
+        archiver: &mut ImageArchiver,
+        interval: Duration,
+        mut on_round: F,
+    ) where
+        F: FnMut(LTAResult<ArchiveReport>) -> ControlFlow<()>,
+        C: Sync,
+    {
+        loop {
+            let started = Instant::now();
+            let report = Self::archive_traffic_images(client, archiver);
+            if on_round(report).is_break() {
+                return;
+            }
+            std::thread::sleep(interval.saturating_sub(started.elapsed()));
+        }
+    }
+
+    /// Returns every carpark, requesting pages of [`CARPARK_PAGE_SIZE`] until the last one
+    ///
+    /// **Update freq**: 1 min
+    fn get_all_carpark_avail(client: &C) -> LTAResult<Vec<CarPark>> {
+        let mut carparks = Vec::new();
+        loop {
+            let page = Self::get_carpark_avail(client, carparks.len() as u32)?;
+            let is_last = page.len() < CARPARK_PAGE_SIZE as usize;
+            carparks.extend(page);
+            if is_last {
+                return Ok(carparks);
+            }
+        }
+    }
+
+    /// Requests every carpark and appends their availability to `recorder`
+    fn record_carpark_avail(
+        client: &C,
+        recorder: &mut AvailabilityRecorder,
+    ) -> LTAResult<RecordReport> {
+        let at = OffsetDateTime::now_utc();
+        let carparks = Self::get_all_carpark_avail(client)?;
+        recorder.record(&carparks, at)
+    }
+
+    /// Runs [`TrafficRequests::record_carpark_avail`] every `interval` until `on_round`
+    /// returns `ControlFlow::Break`. Errors of a round are passed to `on_round` rather than
+    /// stopping the recorder
+    fn record_carpark_avail_every<F>(
+        client: &C,
+        recorder: &mut AvailabilityRecorder,
+        interval: Duration,
+        mut on_round: F,
+    ) where
+        F: FnMut(LTAResult<RecordReport>) -> ControlFlow<()>,
+    {
+        loop {
+            let started = Instant::now();
+            let report = Self::record_carpark_avail(client, recorder);
+            if on_round(report).is_break() {
+                return;
+            }
+            std::thread::sleep(interval.saturating_sub(started.elapsed()));
+        }
     }
 }
@@ -0,0 +1,169 @@
+//! Arrival board that merges the arrivals of several bus stops
+
+use time::OffsetDateTime;
+
+use crate::models::bus::prelude::{BusArrivalResp, NextBus};
+use crate::models::bus_enums::Operator;
+use crate::{LTAError, LTAResult};
+
+/// Default number of stops that are requested at the same time
+pub const DEFAULT_BOARD_CONCURRENCY: usize = 8;
+
+/// One bus on an [`ArrivalBoard`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardEntry {
+    pub bus_stop_code: u32,
+    pub service_no: String,
+    pub operator: Operator,
+
+    /// 0 for the next bus of the service at the stop, 1 and 2 for the ones after
+    pub position: usize,
+    pub bus: NextBus,
+}
+
+impl BoardEntry {
+    pub fn est_arrival(&self) -> OffsetDateTime {
+        self.bus.est_arrival
+    }
+}
+
+/// Upcoming buses at several stops, soonest first
+#[derive(Debug, Default)]
+pub struct ArrivalBoard {
+    pub entries: Vec<BoardEntry>,
+
+    /// Stops whose arrivals could not be requested
+    pub failed: Vec<(u32, LTAError)>,
+}
+
+impl ArrivalBoard {
+    /// Merges the responses of each stop. Buses with the same arrival time are ordered by
+    /// stop, then by service
+    pub fn new(responses: impl IntoIterator<Item = (u32, LTAResult<BusArrivalResp>)>) -> Self {
+        let mut board = ArrivalBoard::default();
+
+        for (bus_stop_code, resp) in responses {
+            let resp = match resp {
+                Ok(resp) => resp,
+                Err(e) => {
+                    board.failed.push((bus_stop_code, e));
+                    continue;
+                }
+            };
+
+            for service in resp.services {
+                for (position, bus) in service.next_bus.into_iter().enumerate() {
+                    let Some(bus) = bus else { continue };
+                    board.entries.push(BoardEntry {
+                        bus_stop_code,
+                        service_no: service.service_no.clone(),
+                        operator: service.operator.clone(),
+                        position,
+                        bus,
+                    });
+                }
+            }
+        }
+
+        board.entries.sort_by(|a, b| {
+            a.bus
+                .est_arrival
+                .cmp(&b.bus.est_arrival)
+                .then(a.bus_stop_code.cmp(&b.bus_stop_code))
+                .then_with(|| a.service_no.cmp(&b.service_no))
+        });
+        board
+    }
+
+    /// Buses arriving at one stop, soonest first
+    pub fn at_stop(&self, bus_stop_code: u32) -> impl Iterator<Item = &BoardEntry> + '_ {
+        self.entries
+            .iter()
+            .filter(move |e| e.bus_stop_code == bus_stop_code)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::bus::bus_arrival::ArrivalBusService;
+    use time::macros::datetime;
+
+    fn bus(est_arrival: OffsetDateTime) -> NextBus {
+        NextBus {
+            origin_code: 0,
+            dest_code: 0,
+            est_arrival,
+            lat: 0.0,
+            long: 0.0,
+            visit_no: 1,
+            load: Default::default(),
+            feature: Default::default(),
+            bus_type: Default::default(),
+        }
+    }
+
+    fn service(service_no: &str, times: &[OffsetDateTime]) -> ArrivalBusService {
+        let mut next_bus = [None, None, None];
+        for (slot, &t) in next_bus.iter_mut().zip(times) {
+            *slot = Some(bus(t));
+        }
+        ArrivalBusService {
+            service_no: service_no.to_string(),
+            operator: Operator::Sbst,
+            next_bus,
+        }
+    }
+
+    #[test]
+    fn merges_by_arrival() {
+        let board = ArrivalBoard::new(vec![
+            (
+                83139,
+                Ok(BusArrivalResp {
+                    bus_stop_code: 83139,
+                    services: vec![service(
+                        "15",
+                        &[
+                            datetime!(2024-03-01 08:32 +8),
+                            datetime!(2024-03-01 08:45 +8),
+                        ],
+                    )],
+                }),
+            ),
+            (1012, Err(LTAError::RateLimitReached)),
+            (
+                83151,
+                Ok(BusArrivalResp {
+                    bus_stop_code: 83151,
+                    services: vec![
+                        service("155", &[datetime!(2024-03-01 08:35 +8)]),
+                        service("43", &[datetime!(2024-03-01 08:31 +8)]),
+                    ],
+                }),
+            ),
+        ]);
+
+        let order = board
+            .entries
+            .iter()
+            .map(|e| (e.bus_stop_code, e.service_no.as_str(), e.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (83151, "43", 0),
+                (83139, "15", 0),
+                (83151, "155", 0),
+                (83139, "15", 1)
+            ]
+        );
+        assert_eq!(board.at_stop(83139).count(), 2);
+        assert_eq!(board.failed.len(), 1);
+        assert_eq!(board.failed[0].0, 1012);
+    }
+}
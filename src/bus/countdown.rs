@@ -0,0 +1,195 @@
+//! Countdowns to the estimated arrivals of buses, in Singapore time
+
+use std::fmt;
+
+use time::{Duration, OffsetDateTime};
+
+use crate::models::bus::bus_arrival::ArrivalBusService;
+use crate::models::bus::prelude::NextBus;
+use crate::utils::{Clock, SGT};
+
+/// How soon a bus arrives, as shown on arrival boards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ArrivalStatus {
+    /// Less than a minute away, or the estimated arrival has passed. Shown as "Arr"
+    Arriving,
+
+    /// One minute away
+    Due,
+
+    /// Whole minutes away, at least 2
+    Minutes(u32),
+}
+
+impl fmt::Display for ArrivalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArrivalStatus::Arriving => f.write_str("Arr"),
+            ArrivalStatus::Due => f.write_str("1 min"),
+            ArrivalStatus::Minutes(m) => write!(f, "{} min", m),
+        }
+    }
+}
+
+/// Time left until a bus arrives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Countdown {
+    pub status: ArrivalStatus,
+
+    /// Negative once the estimated arrival has passed
+    pub remaining: Duration,
+
+    /// Estimated arrival in Singapore time
+    pub est_arrival: OffsetDateTime,
+
+    /// `false` when the estimate comes from the schedule rather than the location of the bus
+    pub monitored: bool,
+}
+
+impl Countdown {
+    pub fn new(bus: &NextBus, now: OffsetDateTime) -> Self {
+        let remaining = bus.est_arrival - now;
+        let status = match remaining.whole_minutes() {
+            m if m < 1 => ArrivalStatus::Arriving,
+            1 => ArrivalStatus::Due,
+            m => ArrivalStatus::Minutes(m.try_into().unwrap_or(u32::MAX)),
+        };
+
+        Countdown {
+            status,
+            remaining,
+            est_arrival: bus.est_arrival.to_offset(SGT),
+            monitored: bus.is_monitored(),
+        }
+    }
+
+    /// Whole minutes left, rounded towards zero. Negative once the estimated arrival has passed
+    pub fn minutes(&self) -> i64 {
+        self.remaining.whole_minutes()
+    }
+
+    pub fn is_scheduled(&self) -> bool {
+        !self.monitored
+    }
+}
+
+/// Countdown helpers for [`NextBus`]
+pub trait NextBusExt {
+    fn countdown(&self, clock: &impl Clock) -> Countdown;
+
+    /// Returns `false` when the location of the bus is unknown, which means that the
+    /// estimated arrival is taken from the schedule
+    fn is_monitored(&self) -> bool;
+
+    /// Estimated arrival in Singapore time
+    fn est_arrival_sgt(&self) -> OffsetDateTime;
+}
+
+impl NextBusExt for NextBus {
+    fn countdown(&self, clock: &impl Clock) -> Countdown {
+        Countdown::new(self, clock.now())
+    }
+
+    fn is_monitored(&self) -> bool {
+        self.lat != 0.0 || self.long != 0.0
+    }
+
+    fn est_arrival_sgt(&self) -> OffsetDateTime {
+        self.est_arrival.to_offset(SGT)
+    }
+}
+
+/// Countdown helpers for [`ArrivalBusService`]
+pub trait ArrivalBusServiceExt {
+    /// Countdowns of the upcoming buses, soonest first. Missing buses are skipped
+    fn countdowns(&self, clock: &impl Clock) -> Vec<Countdown>;
+
+    fn next_countdown(&self, clock: &impl Clock) -> Option<Countdown> {
+        self.countdowns(clock).into_iter().next()
+    }
+}
+
+impl ArrivalBusServiceExt for ArrivalBusService {
+    fn countdowns(&self, clock: &impl Clock) -> Vec<Countdown> {
+        let now = clock.now();
+        let mut countdowns = self
+            .next_bus
+            .iter()
+            .flatten()
+            .map(|bus| Countdown::new(bus, now))
+            .collect::<Vec<_>>();
+        countdowns.sort_by_key(|c| c.remaining);
+        countdowns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn bus(est_arrival: OffsetDateTime, lat: f64, long: f64) -> NextBus {
+        NextBus {
+            origin_code: 0,
+            dest_code: 0,
+            est_arrival,
+            lat,
+            long,
+            visit_no: 1,
+            load: Default::default(),
+            feature: Default::default(),
+            bus_type: Default::default(),
+        }
+    }
+
+    #[test]
+    fn classifies_countdowns() {
+        let now = datetime!(2024-03-01 00:30:00 UTC);
+        let status = |t| bus(t, 1.3, 103.8).countdown(&now).status;
+
+        assert_eq!(
+            status(datetime!(2024-03-01 08:29:30 +8)),
+            ArrivalStatus::Arriving
+        );
+        assert_eq!(
+            status(datetime!(2024-03-01 08:30:59 +8)),
+            ArrivalStatus::Arriving
+        );
+        assert_eq!(
+            status(datetime!(2024-03-01 08:31:00 +8)),
+            ArrivalStatus::Due
+        );
+        assert_eq!(
+            status(datetime!(2024-03-01 08:35:30 +8)),
+            ArrivalStatus::Minutes(5)
+        );
+        assert_eq!(ArrivalStatus::Minutes(5).to_string(), "5 min");
+
+        let passed = bus(datetime!(2024-03-01 08:28:00 +8), 1.3, 103.8).countdown(&now);
+        assert_eq!(passed.minutes(), -2);
+        assert_eq!(passed.est_arrival.offset(), SGT);
+    }
+
+    #[test]
+    fn flags_scheduled_estimates() {
+        let now = datetime!(2024-03-01 08:30 +8);
+        let service = ArrivalBusService {
+            service_no: "15".to_string(),
+            operator: Default::default(),
+            next_bus: [
+                Some(bus(datetime!(2024-03-01 08:40 +8), 0.0, 0.0)),
+                None,
+                Some(bus(datetime!(2024-03-01 08:33 +8), 1.3, 103.8)),
+            ],
+        };
+
+        let countdowns = service.countdowns(&now);
+        assert_eq!(countdowns.len(), 2);
+        assert!(countdowns[0].monitored);
+        assert!(countdowns[1].is_scheduled());
+        assert_eq!(
+            service.next_countdown(&now).unwrap().status,
+            ArrivalStatus::Minutes(3)
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Directory of bus services that joins `get_bus_services`, `get_bus_routes` and
+//! `get_bus_stops`
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::models::bus::prelude::{BusFreq, BusRoute, BusService, BusStop};
+use crate::models::bus_enums::{BusCategory, Operator};
+use crate::models::utils::Coordinates;
+
+/// Dispatch frequencies of one direction of a service
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceFrequencies {
+    pub am_peak: BusFreq,
+    pub am_offpeak: BusFreq,
+    pub pm_peak: BusFreq,
+    pub pm_offpeak: BusFreq,
+}
+
+/// A stop along a direction of a service
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteStop {
+    pub bus_stop_code: u32,
+    pub stop_seq: u8,
+
+    /// Distance from the start of the route, in km
+    pub dist: f64,
+
+    /// `None` when the stop is missing from `get_bus_stops`
+    pub desc: Option<String>,
+    pub road_name: Option<String>,
+    pub location: Option<Coordinates>,
+}
+
+/// One direction of a service
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceDirection {
+    pub direction: u8,
+
+    /// Stops in the order they are called at
+    pub stops: Vec<RouteStop>,
+
+    /// `None` when the direction is missing from `get_bus_services`
+    pub frequencies: Option<ServiceFrequencies>,
+
+    /// Where a loop service turns back, empty for other services
+    pub loop_desc: String,
+}
+
+impl ServiceDirection {
+    pub fn origin(&self) -> Option<&RouteStop> {
+        self.stops.first()
+    }
+
+    pub fn destination(&self) -> Option<&RouteStop> {
+        self.stops.last()
+    }
+
+    /// Returns `true` if the direction ends where it starts
+    pub fn is_loop(&self) -> bool {
+        !self.loop_desc.trim().is_empty()
+            || matches!(
+                (self.origin(), self.destination()),
+                (Some(o), Some(d)) if self.stops.len() > 1 && o.bus_stop_code == d.bus_stop_code
+            )
+    }
+}
+
+/// A service with all of its directions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceInfo {
+    pub service_no: String,
+    pub operator: Operator,
+    pub category: BusCategory,
+
+    /// Ordered by direction
+    pub directions: Vec<ServiceDirection>,
+}
+
+impl ServiceInfo {
+    pub fn direction(&self, direction: u8) -> Option<&ServiceDirection> {
+        self.directions.iter().find(|d| d.direction == direction)
+    }
+
+    /// Returns `true` if any direction of the service ends where it starts
+    pub fn is_loop(&self) -> bool {
+        self.directions.iter().any(ServiceDirection::is_loop)
+    }
+}
+
+/// A service calling at a stop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopCall {
+    pub service_no: String,
+    pub direction: u8,
+    pub stop_seq: u8,
+}
+
+/// Indexed view over the static bus datasets. Build it once from their full results
+#[derive(Debug, Clone, Default)]
+pub struct ServiceDirectory {
+    services: BTreeMap<String, ServiceInfo>,
+    stops: HashMap<u32, BusStop>,
+    calls: HashMap<u32, Vec<StopCall>>,
+}
+
+impl ServiceDirectory {
+    pub fn new(services: &[BusService], routes: &[BusRoute], stops: &[BusStop]) -> Self {
+        let stops = stops
+            .iter()
+            .map(|s| (s.bus_stop_code, s.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut grouped: BTreeMap<&str, BTreeMap<u8, Vec<&BusRoute>>> = BTreeMap::new();
+        for r in routes {
+            grouped
+                .entry(&r.service_no)
+                .or_default()
+                .entry(r.direction)
+                .or_default()
+                .push(r);
+        }
+
+        let mut directory = ServiceDirectory {
+            services: BTreeMap::new(),
+            stops,
+            calls: HashMap::new(),
+        };
+
+        for (service_no, directions) in grouped {
+            // `no_direction` holds the direction that a row of `get_bus_services` describes
+            let rows = services
+                .iter()
+                .filter(|s| s.service_no == service_no)
+                .collect::<Vec<_>>();
+            let (operator, category) = match rows.first() {
+                Some(s) => (s.operator.clone(), s.category.clone()),
+                None => {
+                    let operator = directions.values().flatten().next().map(|r| &r.operator);
+                    (
+                        operator.cloned().unwrap_or_default(),
+                        BusCategory::default(),
+                    )
+                }
+            };
+
+            let directions = directions
+                .into_iter()
+                .map(|(direction, mut rows_of_direction)| {
+                    rows_of_direction.sort_by_key(|r| r.stop_seq);
+                    let service = rows
+                        .iter()
+                        .find(|s| s.no_direction == direction)
+                        .or_else(|| rows.first().filter(|_| rows.len() == 1));
+
+                    for r in &rows_of_direction {
+                        directory
+                            .calls
+                            .entry(r.bus_stop_code)
+                            .or_default()
+                            .push(StopCall {
+                                service_no: service_no.to_string(),
+                                direction,
+                                stop_seq: r.stop_seq,
+                            });
+                    }
+
+                    ServiceDirection {
+                        direction,
+                        stops: rows_of_direction
+                            .iter()
+                            .map(|r| directory.route_stop(r))
+                            .collect(),
+                        frequencies: service.map(|s| ServiceFrequencies {
+                            am_peak: s.am_peak_freq.clone(),
+                            am_offpeak: s.am_offpeak_freq.clone(),
+                            pm_peak: s.pm_peak_freq.clone(),
+                            pm_offpeak: s.pm_offpeak_freq.clone(),
+                        }),
+                        loop_desc: service.map(|s| s.loop_desc.clone()).unwrap_or_default(),
+                    }
+                })
+                .collect();
+
+            directory.services.insert(
+                service_no.to_string(),
+                ServiceInfo {
+                    service_no: service_no.to_string(),
+                    operator,
+                    category,
+                    directions,
+                },
+            );
+        }
+
+        for calls in directory.calls.values_mut() {
+            calls.sort_by(|a, b| {
+                a.service_no
+                    .cmp(&b.service_no)
+                    .then(a.direction.cmp(&b.direction))
+                    .then(a.stop_seq.cmp(&b.stop_seq))
+            });
+        }
+        directory
+    }
+
+    fn route_stop(&self, r: &BusRoute) -> RouteStop {
+        let stop = self.stops.get(&r.bus_stop_code);
+        RouteStop {
+            bus_stop_code: r.bus_stop_code,
+            stop_seq: r.stop_seq,
+            dist: r.dist,
+            desc: stop.map(|s| s.desc.clone()),
+            road_name: stop.map(|s| s.road_name.clone()),
+            location: stop.map(|s| Coordinates::new(s.lat, s.long)),
+        }
+    }
+
+    pub fn service(&self, service_no: &str) -> Option<&ServiceInfo> {
+        self.services.get(service_no)
+    }
+
+    /// Every service, ordered by service number
+    pub fn services(&self) -> impl Iterator<Item = &ServiceInfo> + '_ {
+        self.services.values()
+    }
+
+    /// Services that end where they start in at least one direction
+    pub fn loop_services(&self) -> impl Iterator<Item = &ServiceInfo> + '_ {
+        self.services().filter(|s| s.is_loop())
+    }
+
+    pub fn stop(&self, bus_stop_code: u32) -> Option<&BusStop> {
+        self.stops.get(&bus_stop_code)
+    }
+
+    /// Services calling at a stop, ordered by service and direction. A loop service calls
+    /// twice at the stop it turns back at
+    pub fn services_at(&self, bus_stop_code: u32) -> &[StopCall] {
+        self.calls
+            .get(&bus_stop_code)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(service_no: &str, direction: u8, stops: &[u32]) -> Vec<BusRoute> {
+        stops
+            .iter()
+            .enumerate()
+            .map(|(i, &bus_stop_code)| BusRoute {
+                service_no: service_no.to_string(),
+                operator: Operator::Sbst,
+                direction,
+                stop_seq: i as u8 + 1,
+                bus_stop_code,
+                dist: i as f64,
+                wd_first: None,
+                wd_last: None,
+                sat_first: None,
+                sat_last: None,
+                sun_first: None,
+                sun_last: None,
+            })
+            .collect()
+    }
+
+    fn service(service_no: &str, direction: u8, loop_desc: &str) -> BusService {
+        BusService {
+            service_no: service_no.to_string(),
+            operator: Operator::Sbst,
+            no_direction: direction,
+            category: BusCategory::Trunk,
+            origin_code: None,
+            dest_code: None,
+            am_peak_freq: BusFreq::new(5, 8),
+            am_offpeak_freq: BusFreq::new(8, 12),
+            pm_peak_freq: BusFreq::new(6, 9),
+            pm_offpeak_freq: BusFreq::no_timing(),
+            loop_desc: loop_desc.to_string(),
+        }
+    }
+
+    fn directory() -> ServiceDirectory {
+        let mut routes = route("15", 1, &[1, 2, 3]);
+        routes.extend(route("15", 2, &[3, 4, 1]));
+        routes.extend(route("225G", 1, &[5, 2, 6, 5]));
+
+        let stops = (1..=6)
+            .map(|code| BusStop {
+                bus_stop_code: code,
+                road_name: "Upp Changi Rd".to_string(),
+                desc: format!("Stop {}", code),
+                lat: 1.3,
+                long: 103.9,
+            })
+            .filter(|s| s.bus_stop_code != 4)
+            .collect::<Vec<_>>();
+
+        ServiceDirectory::new(
+            &[
+                service("15", 1, ""),
+                service("15", 2, ""),
+                service("225G", 1, "Bedok Int"),
+            ],
+            &routes,
+            &stops,
+        )
+    }
+
+    #[test]
+    fn service_lookup() {
+        let directory = directory();
+        let service = directory.service("15").unwrap();
+        assert_eq!(service.directions.len(), 2);
+
+        let back = service.direction(2).unwrap();
+        assert_eq!(back.origin().unwrap().bus_stop_code, 3);
+        assert_eq!(back.destination().unwrap().desc.as_deref(), Some("Stop 1"));
+        assert_eq!(back.stops[1].location, None);
+        assert_eq!(
+            back.frequencies.as_ref().unwrap().am_peak,
+            BusFreq::new(5, 8)
+        );
+        assert!(directory.service("16").is_none());
+    }
+
+    #[test]
+    fn stop_lookup_and_loops() {
+        let directory = directory();
+
+        let calls = directory
+            .services_at(2)
+            .iter()
+            .map(|c| (c.service_no.as_str(), c.direction, c.stop_seq))
+            .collect::<Vec<_>>();
+        assert_eq!(calls, vec![("15", 1, 2), ("225G", 1, 2)]);
+        assert_eq!(directory.services_at(5).len(), 2);
+        assert!(directory.services_at(99).is_empty());
+
+        let loops = directory
+            .loop_services()
+            .map(|s| s.service_no.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(loops, vec!["225G"]);
+    }
+}
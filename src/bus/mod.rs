@@ -0,0 +1,17 @@
+pub mod board;
+pub mod countdown;
+pub mod directory;
+pub mod network;
+pub mod stops;
+pub mod timings;
+pub mod watch;
+
+pub use board::{ArrivalBoard, BoardEntry, DEFAULT_BOARD_CONCURRENCY};
+pub use countdown::{ArrivalBusServiceExt, ArrivalStatus, Countdown, NextBusExt};
+pub use directory::{
+    RouteStop, ServiceDirection, ServiceDirectory, ServiceFrequencies, ServiceInfo, StopCall,
+};
+pub use network::{BusNetwork, Cost, Journey, Leg, PlanOptions};
+pub use stops::BusStopIndex;
+pub use timings::{BusTimetable, ServiceState, ServiceStatus};
+pub use watch::{ArrivalEvent, ArrivalWatcher, WatchOptions};
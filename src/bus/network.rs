@@ -0,0 +1,532 @@
+//! Bus network graph built from `BusRoute` and `BusStop`, with a journey planner
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::geo::{Located, SpatialIndex};
+use crate::models::bus::prelude::{BusRoute, BusStop};
+
+/// What the planner minimises
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum Cost {
+    /// Distance travelled by bus and on foot
+    #[default]
+    Distance,
+
+    /// Number of stops travelled by bus
+    Stops,
+}
+
+/// Options for [`BusNetwork::plan`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlanOptions {
+    pub cost: Cost,
+
+    /// Maximum number of changes between buses
+    pub max_transfers: usize,
+
+    /// Allows walking to another stop within this many metres between buses, and before the
+    /// first bus. `None` disables walking
+    pub walk_radius: Option<f64>,
+}
+
+impl Default for PlanOptions {
+    fn default() -> Self {
+        PlanOptions {
+            cost: Cost::Distance,
+            max_transfers: 2,
+            walk_radius: None,
+        }
+    }
+}
+
+/// Part of a journey
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Leg {
+    Bus {
+        service_no: String,
+        direction: u8,
+
+        /// Stops along the leg, from the boarding stop to the alighting stop
+        stops: Vec<u32>,
+
+        /// In metres
+        distance: f64,
+    },
+    Walk {
+        from: u32,
+        to: u32,
+
+        /// Straight line distance, in metres
+        distance: f64,
+    },
+}
+
+/// A planned journey between two bus stops
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Journey {
+    pub legs: Vec<Leg>,
+
+    /// Total distance by bus and on foot, in metres
+    pub distance: f64,
+
+    /// Number of stops travelled by bus
+    pub stops: usize,
+}
+
+impl Journey {
+    /// Number of changes between buses
+    pub fn transfers(&self) -> usize {
+        self.legs
+            .iter()
+            .filter(|l| matches!(l, Leg::Bus { .. }))
+            .count()
+            .saturating_sub(1)
+    }
+}
+
+/// Stops of one direction of a service, in order
+#[derive(Debug, Clone, PartialEq)]
+struct ServiceRoute {
+    service_no: String,
+    direction: u8,
+    stops: Vec<u32>,
+
+    /// Distance from the start of the route to each stop, in metres
+    dist: Vec<f64>,
+}
+
+/// Directed graph of bus services. Build it once from the full results of `get_bus_routes` and
+/// `get_bus_stops`
+#[derive(Debug, Clone)]
+pub struct BusNetwork {
+    routes: Vec<ServiceRoute>,
+
+    /// Stop code to the (route, position) pairs that serve it
+    serving: HashMap<u32, Vec<(usize, usize)>>,
+    stops: SpatialIndex<BusStop>,
+    stop_idx: HashMap<u32, usize>,
+}
+
+impl BusNetwork {
+    pub fn new(routes: &[BusRoute], stops: Vec<BusStop>) -> Self {
+        let mut grouped: HashMap<(&str, u8), Vec<&BusRoute>> = HashMap::new();
+        for r in routes {
+            grouped
+                .entry((r.service_no.as_str(), r.direction))
+                .or_default()
+                .push(r);
+        }
+
+        let mut keys = grouped.keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+
+        let routes = keys
+            .into_iter()
+            .map(|key| {
+                let mut rows = grouped.remove(&key).unwrap_or_default();
+                rows.sort_by_key(|r| r.stop_seq);
+                ServiceRoute {
+                    service_no: key.0.to_string(),
+                    direction: key.1,
+                    stops: rows.iter().map(|r| r.bus_stop_code).collect(),
+                    dist: rows.iter().map(|r| r.dist * 1000.0).collect(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut serving: HashMap<u32, Vec<(usize, usize)>> = HashMap::new();
+        for (ri, route) in routes.iter().enumerate() {
+            for (pos, &stop) in route.stops.iter().enumerate() {
+                serving.entry(stop).or_default().push((ri, pos));
+            }
+        }
+
+        let stop_idx = stops
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.bus_stop_code, i))
+            .collect();
+
+        BusNetwork {
+            routes,
+            serving,
+            stops: SpatialIndex::new(stops),
+            stop_idx,
+        }
+    }
+
+    /// Returns the stops of a service in one direction, in order
+    pub fn route(&self, service_no: &str, direction: u8) -> Option<&[u32]> {
+        self.routes
+            .iter()
+            .find(|r| r.service_no == service_no && r.direction == direction)
+            .map(|r| r.stops.as_slice())
+    }
+
+    /// Returns the services, with their direction, that call at a stop
+    pub fn services_at(&self, bus_stop_code: u32) -> Vec<(&str, u8)> {
+        let mut services = self
+            .serving
+            .get(&bus_stop_code)
+            .into_iter()
+            .flatten()
+            .map(|&(ri, _)| {
+                (
+                    self.routes[ri].service_no.as_str(),
+                    self.routes[ri].direction,
+                )
+            })
+            .collect::<Vec<_>>();
+        services.dedup();
+        services
+    }
+
+    /// Returns the cheapest journey from `from` to `to` according to `options`. Ties are
+    /// broken by the number of transfers. `None` if no journey is possible
+    pub fn plan(&self, from: u32, to: u32, options: &PlanOptions) -> Option<Journey> {
+        let max_legs = options
+            .max_transfers
+            .saturating_add(1)
+            .min(u8::MAX as usize) as u8;
+        let start = Node::Stop {
+            stop: from,
+            legs: 0,
+            walked: false,
+        };
+
+        let mut best: HashMap<Node, f64> = HashMap::from([(start, 0.0)]);
+        let mut prev: HashMap<Node, (Node, Step)> = HashMap::new();
+        let mut heap = BinaryHeap::from([State {
+            cost: 0.0,
+            node: start,
+        }]);
+
+        while let Some(State { cost, node }) = heap.pop() {
+            if best.get(&node).is_some_and(|&c| cost > c) {
+                continue;
+            }
+
+            if let Node::Stop { stop, .. } = node {
+                if stop == to {
+                    return Some(self.journey(node, &prev));
+                }
+            }
+
+            for (next, step, step_cost) in self.edges(node, max_legs, options) {
+                let next_cost = cost + step_cost;
+                if best.get(&next).is_none_or(|&c| next_cost < c) {
+                    best.insert(next, next_cost);
+                    prev.insert(next, (node, step));
+                    heap.push(State {
+                        cost: next_cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    fn edges(&self, node: Node, max_legs: u8, options: &PlanOptions) -> Vec<(Node, Step, f64)> {
+        let mut edges = Vec::new();
+
+        match node {
+            Node::Stop { stop, legs, walked } => {
+                if legs < max_legs {
+                    for &(route, pos) in self.serving.get(&stop).into_iter().flatten() {
+                        let legs = legs + 1;
+                        edges.push((Node::Riding { route, pos, legs }, Step::Board, 0.0));
+                    }
+                }
+
+                if let (false, Some(radius)) = (walked, options.walk_radius) {
+                    let Some(&i) = self.stop_idx.get(&stop) else {
+                        return edges;
+                    };
+                    let here = self.stops.items()[i].coordinates();
+
+                    for n in self.stops.within(&here, radius) {
+                        if n.item.bus_stop_code == stop {
+                            continue;
+                        }
+                        let next = Node::Stop {
+                            stop: n.item.bus_stop_code,
+                            legs,
+                            walked: true,
+                        };
+                        let cost = match options.cost {
+                            Cost::Distance => n.distance,
+                            Cost::Stops => 0.0,
+                        };
+                        edges.push((next, Step::Walk(n.distance), cost));
+                    }
+                }
+            }
+            Node::Riding { route, pos, legs } => {
+                let r = &self.routes[route];
+                let stop = Node::Stop {
+                    stop: r.stops[pos],
+                    legs,
+                    walked: false,
+                };
+                edges.push((stop, Step::Alight, 0.0));
+
+                if pos + 1 < r.stops.len() {
+                    let distance = (r.dist[pos + 1] - r.dist[pos]).max(0.0);
+                    let cost = match options.cost {
+                        Cost::Distance => distance,
+                        Cost::Stops => 1.0,
+                    };
+                    let next = Node::Riding {
+                        route,
+                        pos: pos + 1,
+                        legs,
+                    };
+                    edges.push((next, Step::Ride(distance), cost));
+                }
+            }
+        }
+
+        edges
+    }
+
+    fn journey(&self, end: Node, prev: &HashMap<Node, (Node, Step)>) -> Journey {
+        let mut steps = Vec::new();
+        let mut node = end;
+        while let Some(&(p, step)) = prev.get(&node) {
+            steps.push((p, step, node));
+            node = p;
+        }
+        steps.reverse();
+
+        let mut journey = Journey {
+            legs: Vec::new(),
+            distance: 0.0,
+            stops: 0,
+        };
+
+        for (from, step, to) in steps {
+            match (step, to) {
+                (Step::Board, Node::Riding { route, pos, .. }) => {
+                    let r = &self.routes[route];
+                    journey.legs.push(Leg::Bus {
+                        service_no: r.service_no.clone(),
+                        direction: r.direction,
+                        stops: vec![r.stops[pos]],
+                        distance: 0.0,
+                    });
+                }
+                (Step::Ride(d), Node::Riding { route, pos, .. }) => {
+                    if let Some(Leg::Bus {
+                        stops, distance, ..
+                    }) = journey.legs.last_mut()
+                    {
+                        stops.push(self.routes[route].stops[pos]);
+                        *distance += d;
+                    }
+                    journey.distance += d;
+                    journey.stops += 1;
+                }
+                (Step::Walk(d), Node::Stop { stop: to, .. }) => {
+                    if let Node::Stop { stop: from, .. } = from {
+                        journey.legs.push(Leg::Walk {
+                            from,
+                            to,
+                            distance: d,
+                        });
+                    }
+                    journey.distance += d;
+                }
+                _ => {}
+            }
+        }
+
+        journey
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    /// At a stop, either at the start, after alighting or after walking
+    Stop { stop: u32, legs: u8, walked: bool },
+
+    /// On a bus at `pos` of `route`
+    Riding { route: usize, pos: usize, legs: u8 },
+}
+
+impl Node {
+    fn legs(&self) -> u8 {
+        match *self {
+            Node::Stop { legs, .. } | Node::Riding { legs, .. } => legs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    Board,
+    Ride(f64),
+    Alight,
+    Walk(f64),
+}
+
+/// Entry of the priority queue, ordered so that the cheapest state with the fewest legs
+/// is popped first
+#[derive(Debug, Clone, Copy)]
+struct State {
+    cost: f64,
+    node: Node,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for State {}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.node.legs().cmp(&self.node.legs()))
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(service_no: &str, stops: &[(u32, f64)]) -> Vec<BusRoute> {
+        stops
+            .iter()
+            .enumerate()
+            .map(|(i, &(bus_stop_code, dist))| BusRoute {
+                service_no: service_no.to_string(),
+                operator: Default::default(),
+                direction: 1,
+                stop_seq: i as u8 + 1,
+                bus_stop_code,
+                dist,
+                wd_first: None,
+                wd_last: None,
+                sat_first: None,
+                sat_last: None,
+                sun_first: None,
+                sun_last: None,
+            })
+            .collect()
+    }
+
+    fn stop(bus_stop_code: u32, lat: f64, long: f64) -> BusStop {
+        BusStop {
+            bus_stop_code,
+            road_name: String::new(),
+            desc: String::new(),
+            lat,
+            long,
+        }
+    }
+
+    /// 10 runs 1 -> 2 -> 3 -> 4, 20 runs 3 -> 5 -> 6, 30 runs 1 -> 6 the long way round,
+    /// 40 runs 7 -> 8 where 7 is about 110m from 6
+    fn network() -> BusNetwork {
+        let mut routes = route("10", &[(1, 0.0), (2, 1.0), (3, 2.0), (4, 3.0)]);
+        routes.extend(route("20", &[(3, 0.0), (5, 1.0), (6, 2.0)]));
+        routes.extend(route("30", &[(1, 0.0), (6, 9.0)]));
+        routes.extend(route("40", &[(7, 0.0), (8, 1.0)]));
+
+        let stops = vec![
+            stop(1, 1.30, 103.80),
+            stop(2, 1.31, 103.80),
+            stop(3, 1.32, 103.80),
+            stop(4, 1.33, 103.80),
+            stop(5, 1.32, 103.81),
+            stop(6, 1.32, 103.82),
+            stop(7, 1.321, 103.82),
+            stop(8, 1.33, 103.83),
+        ];
+
+        BusNetwork::new(&routes, stops)
+    }
+
+    fn services(journey: &Journey) -> Vec<&str> {
+        journey
+            .legs
+            .iter()
+            .map(|l| match l {
+                Leg::Bus { service_no, .. } => service_no.as_str(),
+                Leg::Walk { .. } => "walk",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn direct_and_transfer() {
+        let network = network();
+
+        let direct = network.plan(1, 4, &PlanOptions::default()).unwrap();
+        assert_eq!(services(&direct), vec!["10"]);
+        assert_eq!(direct.stops, 3);
+        assert_eq!(direct.distance, 3000.0);
+
+        let transfer = network.plan(1, 6, &PlanOptions::default()).unwrap();
+        assert_eq!(services(&transfer), vec!["10", "20"]);
+        assert_eq!(transfer.transfers(), 1);
+        assert_eq!(transfer.distance, 4000.0);
+        match &transfer.legs[1] {
+            Leg::Bus { stops, .. } => assert_eq!(stops, &vec![3, 5, 6]),
+            l => panic!("Unexpected leg {:?}", l),
+        }
+    }
+
+    #[test]
+    fn cost_and_transfer_limits() {
+        let network = network();
+
+        let by_stops = PlanOptions {
+            cost: Cost::Stops,
+            ..Default::default()
+        };
+        assert_eq!(
+            services(&network.plan(1, 6, &by_stops).unwrap()),
+            vec!["30"]
+        );
+
+        let no_transfers = PlanOptions {
+            max_transfers: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            services(&network.plan(1, 6, &no_transfers).unwrap()),
+            vec!["30"]
+        );
+        assert!(network.plan(4, 1, &no_transfers).is_none());
+    }
+
+    #[test]
+    fn walking_transfer() {
+        let network = network();
+        assert!(network.plan(1, 8, &PlanOptions::default()).is_none());
+
+        let walk = PlanOptions {
+            walk_radius: Some(200.0),
+            ..Default::default()
+        };
+        let journey = network.plan(1, 8, &walk).unwrap();
+        assert_eq!(services(&journey), vec!["10", "20", "walk", "40"]);
+        assert!(journey.distance > 5000.0 && journey.distance < 5200.0);
+    }
+}
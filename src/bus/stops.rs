@@ -0,0 +1,15 @@
+//! Offline lookups over the bus stops returned by `get_bus_stops`
+
+use crate::geo::{Located, SpatialIndex};
+use crate::models::bus::prelude::BusStop;
+use crate::models::utils::Coordinates;
+
+/// Spatial index over bus stops, see [`SpatialIndex`] for the queries it supports.
+/// Build it once from the result of `get_bus_stops` and share it between requests
+pub type BusStopIndex = SpatialIndex<BusStop>;
+
+impl Located for BusStop {
+    fn coordinates(&self) -> Coordinates {
+        Coordinates::new(self.lat, self.long)
+    }
+}
@@ -0,0 +1,294 @@
+//! Whether a service is operating at a stop, from the first and last bus timings of
+//! `get_bus_routes`
+
+use std::collections::HashMap;
+
+use time::{Date, Duration, OffsetDateTime, Time};
+
+use crate::models::bus::prelude::BusRoute;
+use crate::utils::{DayType, SGT};
+
+/// Number of days looked ahead for the next first bus
+const LOOKAHEAD_DAYS: i64 = 7;
+
+/// Whether a service is operating at a stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    /// Operating until the last bus, in SGT
+    Running { last_bus: OffsetDateTime },
+
+    /// Not operating. The first bus, in SGT, is `None` when the service does not call at the
+    /// stop in the coming week
+    NotRunning {
+        next_first_bus: Option<OffsetDateTime>,
+    },
+}
+
+/// State of a service at a stop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceStatus {
+    pub service_no: String,
+    pub direction: u8,
+    pub bus_stop_code: u32,
+    pub state: ServiceState,
+}
+
+impl ServiceStatus {
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, ServiceState::Running { .. })
+    }
+}
+
+/// First and last bus of each day type
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StopTimings {
+    service_no: String,
+    direction: u8,
+    weekday: Option<(Time, Time)>,
+    saturday: Option<(Time, Time)>,
+    sunday: Option<(Time, Time)>,
+}
+
+impl StopTimings {
+    fn first_last(&self, day_type: DayType) -> Option<(Time, Time)> {
+        match day_type {
+            DayType::Weekday => self.weekday,
+            DayType::Saturday => self.saturday,
+            DayType::SundayOrHoliday => self.sunday,
+        }
+    }
+
+    /// Operating window of the service day that starts on `date`
+    fn window(&self, date: Date) -> Option<(OffsetDateTime, OffsetDateTime)> {
+        let (first, last) = self.first_last(DayType::for_date(date))?;
+        let start = date.with_time(first).assume_offset(SGT);
+        Some((start, start + operating_span(first, last)))
+    }
+
+    fn state(&self, at: OffsetDateTime) -> ServiceState {
+        let today = at.to_offset(SGT).date();
+
+        // Yesterday's service day may not have ended yet
+        for date in [today.previous_day(), Some(today)].into_iter().flatten() {
+            if let Some((start, end)) = self.window(date) {
+                if (start..=end).contains(&at) {
+                    return ServiceState::Running { last_bus: end };
+                }
+            }
+        }
+
+        let next_first_bus = (0..=LOOKAHEAD_DAYS)
+            .filter_map(|d| today.checked_add(Duration::days(d)))
+            .filter_map(|date| self.window(date))
+            .map(|(start, _)| start)
+            .find(|&start| start > at);
+        ServiceState::NotRunning { next_first_bus }
+    }
+}
+
+/// Time from the first to the last bus. A last bus before the first bus runs past midnight,
+/// while one at the same time as the first bus is the only trip of the day
+pub(crate) fn operating_span(first: Time, last: Time) -> Duration {
+    let span = last - first;
+    if span.is_negative() {
+        span + Duration::DAY
+    } else {
+        span
+    }
+}
+
+/// First and last bus timings of every service at every stop. Build it once from the full
+/// result of `get_bus_routes`
+#[derive(Debug, Clone, Default)]
+pub struct BusTimetable {
+    stops: HashMap<u32, Vec<StopTimings>>,
+}
+
+impl BusTimetable {
+    pub fn new(routes: &[BusRoute]) -> Self {
+        let mut stops: HashMap<u32, Vec<StopTimings>> = HashMap::new();
+        for r in routes {
+            let timings = StopTimings {
+                service_no: r.service_no.clone(),
+                direction: r.direction,
+                weekday: r.wd_first.zip(r.wd_last),
+                saturday: r.sat_first.zip(r.sat_last),
+                sunday: r.sun_first.zip(r.sun_last),
+            };
+
+            let at_stop = stops.entry(r.bus_stop_code).or_default();
+            // Loops call at their first stop twice, keep the first visit
+            if !at_stop
+                .iter()
+                .any(|t| t.service_no == timings.service_no && t.direction == timings.direction)
+            {
+                at_stop.push(timings);
+            }
+        }
+
+        for at_stop in stops.values_mut() {
+            at_stop.sort_by(|a, b| {
+                a.service_no
+                    .cmp(&b.service_no)
+                    .then(a.direction.cmp(&b.direction))
+            });
+        }
+        BusTimetable { stops }
+    }
+
+    /// State of a service at a stop at `at`. `None` if the service does not call at the stop
+    pub fn status(
+        &self,
+        service_no: &str,
+        direction: u8,
+        bus_stop_code: u32,
+        at: OffsetDateTime,
+    ) -> Option<ServiceStatus> {
+        self.stops
+            .get(&bus_stop_code)?
+            .iter()
+            .find(|t| t.service_no == service_no && t.direction == direction)
+            .map(|t| status(t, bus_stop_code, at))
+    }
+
+    /// States of every service that calls at a stop at `at`, ordered by service
+    pub fn statuses_at(&self, bus_stop_code: u32, at: OffsetDateTime) -> Vec<ServiceStatus> {
+        self.stops
+            .get(&bus_stop_code)
+            .into_iter()
+            .flatten()
+            .map(|t| status(t, bus_stop_code, at))
+            .collect()
+    }
+}
+
+fn status(timings: &StopTimings, bus_stop_code: u32, at: OffsetDateTime) -> ServiceStatus {
+    ServiceStatus {
+        service_no: timings.service_no.clone(),
+        direction: timings.direction,
+        bus_stop_code,
+        state: timings.state(at),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::{datetime, time};
+
+    fn route(
+        service_no: &str,
+        weekday: Option<(Time, Time)>,
+        saturday: Option<(Time, Time)>,
+        sunday: Option<(Time, Time)>,
+    ) -> BusRoute {
+        BusRoute {
+            service_no: service_no.to_string(),
+            operator: Default::default(),
+            direction: 1,
+            stop_seq: 1,
+            bus_stop_code: 83139,
+            dist: 0.0,
+            wd_first: weekday.map(|t| t.0),
+            wd_last: weekday.map(|t| t.1),
+            sat_first: saturday.map(|t| t.0),
+            sat_last: saturday.map(|t| t.1),
+            sun_first: sunday.map(|t| t.0),
+            sun_last: sunday.map(|t| t.1),
+        }
+    }
+
+    fn timetable() -> BusTimetable {
+        let late = Some((time!(05:30), time!(00:30)));
+        BusTimetable::new(&[
+            route("15", late, late, Some((time!(06:00), time!(23:30)))),
+            route("10e", Some((time!(07:00), time!(09:00))), None, None),
+            route("652", Some((time!(07:15), time!(07:15))), None, None),
+        ])
+    }
+
+    #[test]
+    fn past_midnight() {
+        let timetable = timetable();
+
+        // Friday 00:15 is still part of Thursday's service day
+        let status = timetable
+            .status("15", 1, 83139, datetime!(2024-03-01 00:15 +8))
+            .unwrap();
+        assert_eq!(
+            status.state,
+            ServiceState::Running {
+                last_bus: datetime!(2024-03-01 00:30 +8)
+            }
+        );
+
+        let status = timetable
+            .status("15", 1, 83139, datetime!(2024-03-01 01:00 +8))
+            .unwrap();
+        assert_eq!(
+            status.state,
+            ServiceState::NotRunning {
+                next_first_bus: Some(datetime!(2024-03-01 05:30 +8))
+            }
+        );
+
+        // Sunday's last bus does not run past midnight
+        let status = timetable
+            .status("15", 1, 83139, datetime!(2024-03-03 23:45 +8))
+            .unwrap();
+        assert!(!status.is_running());
+    }
+
+    #[test]
+    fn single_trip() {
+        let timetable = timetable();
+
+        let status = timetable
+            .status("652", 1, 83139, datetime!(2024-03-01 07:15 +8))
+            .unwrap();
+        assert_eq!(
+            status.state,
+            ServiceState::Running {
+                last_bus: datetime!(2024-03-01 07:15 +8)
+            }
+        );
+
+        // The next trip is on Monday
+        let status = timetable
+            .status("652", 1, 83139, datetime!(2024-03-01 07:16 +8))
+            .unwrap();
+        assert_eq!(
+            status.state,
+            ServiceState::NotRunning {
+                next_first_bus: Some(datetime!(2024-03-04 07:15 +8))
+            }
+        );
+    }
+
+    #[test]
+    fn day_types_and_all_services() {
+        let timetable = timetable();
+
+        // Saturday, so the weekday only service is next running on Monday
+        let statuses = timetable.statuses_at(83139, datetime!(2024-03-02 08:00 UTC));
+        assert_eq!(statuses.len(), 3);
+        assert_eq!(statuses[0].service_no, "10e");
+        assert_eq!(
+            statuses[0].state,
+            ServiceState::NotRunning {
+                next_first_bus: Some(datetime!(2024-03-04 07:00 +8))
+            }
+        );
+        assert!(statuses[1].is_running());
+
+        // Good Friday follows the Sunday timings
+        assert!(!timetable
+            .status("10e", 1, 83139, datetime!(2024-03-29 08:00 +8))
+            .unwrap()
+            .is_running());
+
+        assert!(timetable
+            .status("15", 2, 83139, datetime!(2024-03-02 08:00 +8))
+            .is_none());
+    }
+}
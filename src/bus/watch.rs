@@ -0,0 +1,356 @@
+//! Change detection between successive bus arrival responses
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use crate::models::bus::prelude::{BusArrivalResp, NextBus};
+use crate::models::bus_enums::BusLoad;
+
+/// Options for `watch_arrival`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatchOptions {
+    /// Time between requests. The arrival API updates every minute
+    pub interval: Duration,
+
+    /// A bus is approaching once it is estimated to arrive within this long
+    pub approaching: Duration,
+
+    /// Smallest change in the estimated arrival of a bus that is reported
+    pub eta_shift: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            interval: Duration::from_secs(60),
+            approaching: Duration::from_secs(120),
+            eta_shift: Duration::from_secs(120),
+        }
+    }
+}
+
+/// A change in the arrivals of a service at the watched stop
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrivalEvent {
+    /// The bus is estimated to arrive within [`WatchOptions::approaching`]
+    Approaching { service_no: String, bus: NextBus },
+
+    /// The estimated arrival moved by more than [`WatchOptions::eta_shift`]
+    EtaShifted {
+        service_no: String,
+        bus: NextBus,
+        previous: OffsetDateTime,
+    },
+
+    /// The estimated arrival of the bus has passed and it is still listed
+    Arrived { service_no: String, bus: NextBus },
+
+    /// The bus is no longer listed, it has left the stop
+    Departed { service_no: String, bus: NextBus },
+
+    LoadChanged {
+        service_no: String,
+        bus: NextBus,
+        previous: BusLoad,
+    },
+
+    /// The service no longer lists any bus, usually as it has stopped operating for the day
+    ServiceStopped { service_no: String },
+}
+
+impl ArrivalEvent {
+    pub fn service_no(&self) -> &str {
+        match self {
+            ArrivalEvent::Approaching { service_no, .. }
+            | ArrivalEvent::EtaShifted { service_no, .. }
+            | ArrivalEvent::Arrived { service_no, .. }
+            | ArrivalEvent::Departed { service_no, .. }
+            | ArrivalEvent::LoadChanged { service_no, .. }
+            | ArrivalEvent::ServiceStopped { service_no } => service_no,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Tracked {
+    bus: NextBus,
+    approached: bool,
+    arrived: bool,
+}
+
+/// Turns successive responses of one stop into [`ArrivalEvent`]s. The API does not identify
+/// buses, so buses are matched between responses by their position and estimated arrival
+#[derive(Debug, Clone, Default)]
+pub struct ArrivalWatcher {
+    options: WatchOptions,
+    services: HashMap<String, Vec<Tracked>>,
+}
+
+impl ArrivalWatcher {
+    pub fn new(options: WatchOptions) -> Self {
+        ArrivalWatcher {
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub fn options(&self) -> &WatchOptions {
+        &self.options
+    }
+
+    /// Compares `resp` with the previous response. The first response only reports the buses
+    /// that are already approaching or have arrived
+    pub fn update(&mut self, resp: &BusArrivalResp, now: OffsetDateTime) -> Vec<ArrivalEvent> {
+        let mut events = Vec::new();
+        let mut seen = Vec::new();
+
+        for service in &resp.services {
+            let current = service
+                .next_bus
+                .iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>();
+            if current.is_empty() {
+                continue;
+            }
+
+            let previous = self
+                .services
+                .remove(&service.service_no)
+                .unwrap_or_default();
+            let tracked = self.compare(&service.service_no, previous, current, now, &mut events);
+            self.services.insert(service.service_no.clone(), tracked);
+            seen.push(service.service_no.as_str());
+        }
+
+        let mut stopped = self
+            .services
+            .keys()
+            .filter(|s| !seen.contains(&s.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        stopped.sort_unstable();
+        for service_no in stopped {
+            self.services.remove(&service_no);
+            events.push(ArrivalEvent::ServiceStopped { service_no });
+        }
+
+        events
+    }
+
+    fn compare(
+        &self,
+        service_no: &str,
+        previous: Vec<Tracked>,
+        current: Vec<NextBus>,
+        now: OffsetDateTime,
+        events: &mut Vec<ArrivalEvent>,
+    ) -> Vec<Tracked> {
+        // Buses ahead of the one that is now listed first have left the stop
+        let first = current[0].est_arrival;
+        let mut departed = 0;
+        while departed + 1 < previous.len()
+            && (first - previous[departed + 1].bus.est_arrival).abs()
+                < (first - previous[departed].bus.est_arrival).abs()
+        {
+            departed += 1;
+        }
+
+        // A bus that has arrived and is now followed by a much later estimate has left, even
+        // when there is no later bus in the previous response to match against
+        while departed < previous.len()
+            && previous[departed].arrived
+            && first - previous[departed].bus.est_arrival > self.options.eta_shift
+        {
+            departed += 1;
+        }
+
+        let mut previous = previous.into_iter();
+        for gone in previous.by_ref().take(departed) {
+            events.push(ArrivalEvent::Departed {
+                service_no: service_no.to_string(),
+                bus: gone.bus,
+            });
+        }
+
+        let mut tracked = Vec::with_capacity(current.len());
+        for bus in current {
+            let mut t = match previous.next() {
+                Some(prev) => {
+                    if (bus.est_arrival - prev.bus.est_arrival).abs() > self.options.eta_shift {
+                        events.push(ArrivalEvent::EtaShifted {
+                            service_no: service_no.to_string(),
+                            bus: bus.clone(),
+                            previous: prev.bus.est_arrival,
+                        });
+                    }
+                    if bus.load != prev.bus.load {
+                        events.push(ArrivalEvent::LoadChanged {
+                            service_no: service_no.to_string(),
+                            bus: bus.clone(),
+                            previous: prev.bus.load.clone(),
+                        });
+                    }
+                    Tracked { bus, ..prev }
+                }
+                None => Tracked {
+                    bus,
+                    approached: false,
+                    arrived: false,
+                },
+            };
+
+            let eta = t.bus.est_arrival - now;
+            if !t.arrived && eta <= time::Duration::ZERO {
+                t.arrived = true;
+                t.approached = true;
+                events.push(ArrivalEvent::Arrived {
+                    service_no: service_no.to_string(),
+                    bus: t.bus.clone(),
+                });
+            } else if !t.approached && eta <= self.options.approaching {
+                t.approached = true;
+                events.push(ArrivalEvent::Approaching {
+                    service_no: service_no.to_string(),
+                    bus: t.bus.clone(),
+                });
+            }
+            tracked.push(t);
+        }
+
+        tracked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::bus::bus_arrival::ArrivalBusService;
+    use time::macros::datetime;
+
+    fn bus(est_arrival: OffsetDateTime, load: BusLoad) -> NextBus {
+        NextBus {
+            origin_code: 0,
+            dest_code: 0,
+            est_arrival,
+            lat: 0.0,
+            long: 0.0,
+            visit_no: 1,
+            load,
+            feature: Default::default(),
+            bus_type: Default::default(),
+        }
+    }
+
+    fn resp(buses: &[(OffsetDateTime, BusLoad)]) -> BusArrivalResp {
+        let mut next_bus = [None, None, None];
+        for (slot, (t, load)) in next_bus.iter_mut().zip(buses) {
+            *slot = Some(bus(*t, load.clone()));
+        }
+        BusArrivalResp {
+            bus_stop_code: 83139,
+            services: vec![ArrivalBusService {
+                service_no: "15".to_string(),
+                operator: Default::default(),
+                next_bus,
+            }],
+        }
+    }
+
+    fn kinds(events: &[ArrivalEvent]) -> Vec<&'static str> {
+        events
+            .iter()
+            .map(|e| match e {
+                ArrivalEvent::Approaching { .. } => "approaching",
+                ArrivalEvent::EtaShifted { .. } => "shifted",
+                ArrivalEvent::Arrived { .. } => "arrived",
+                ArrivalEvent::Departed { .. } => "departed",
+                ArrivalEvent::LoadChanged { .. } => "load",
+                ArrivalEvent::ServiceStopped { .. } => "stopped",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detects_changes() {
+        use BusLoad::*;
+        let mut watcher = ArrivalWatcher::new(WatchOptions::default());
+
+        let events = watcher.update(
+            &resp(&[
+                (datetime!(2024-03-01 08:35 +8), SeatsAvailable),
+                (datetime!(2024-03-01 08:45 +8), SeatsAvailable),
+            ]),
+            datetime!(2024-03-01 08:30 +8),
+        );
+        assert!(events.is_empty());
+
+        let events = watcher.update(
+            &resp(&[
+                (datetime!(2024-03-01 08:34 +8), StandingAvailable),
+                (datetime!(2024-03-01 08:50 +8), SeatsAvailable),
+            ]),
+            datetime!(2024-03-01 08:32 +8),
+        );
+        assert_eq!(kinds(&events), vec!["load", "approaching", "shifted"]);
+
+        let events = watcher.update(
+            &resp(&[
+                (datetime!(2024-03-01 08:34 +8), StandingAvailable),
+                (datetime!(2024-03-01 08:50 +8), SeatsAvailable),
+            ]),
+            datetime!(2024-03-01 08:34:30 +8),
+        );
+        assert_eq!(kinds(&events), vec!["arrived"]);
+
+        let events = watcher.update(
+            &resp(&[(datetime!(2024-03-01 08:51 +8), SeatsAvailable)]),
+            datetime!(2024-03-01 08:36 +8),
+        );
+        assert_eq!(kinds(&events), vec!["departed"]);
+
+        let events = watcher.update(&resp(&[]), datetime!(2024-03-01 08:37 +8));
+        assert_eq!(kinds(&events), vec!["stopped"]);
+        assert_eq!(events[0].service_no(), "15");
+    }
+
+    #[test]
+    fn single_bus_departs() {
+        use BusLoad::*;
+        let mut watcher = ArrivalWatcher::new(WatchOptions::default());
+
+        let events = watcher.update(
+            &resp(&[(datetime!(2024-03-01 08:31 +8), SeatsAvailable)]),
+            datetime!(2024-03-01 08:30 +8),
+        );
+        assert_eq!(kinds(&events), vec!["approaching"]);
+
+        let events = watcher.update(
+            &resp(&[(datetime!(2024-03-01 08:31 +8), SeatsAvailable)]),
+            datetime!(2024-03-01 08:31:30 +8),
+        );
+        assert_eq!(kinds(&events), vec!["arrived"]);
+
+        // The next listing only has the following bus
+        let events = watcher.update(
+            &resp(&[(datetime!(2024-03-01 08:52 +8), SeatsAvailable)]),
+            datetime!(2024-03-01 08:33 +8),
+        );
+        assert_eq!(kinds(&events), vec!["departed"]);
+
+        let events = watcher.update(
+            &resp(&[(datetime!(2024-03-01 08:52 +8), SeatsAvailable)]),
+            datetime!(2024-03-01 08:50:30 +8),
+        );
+        assert_eq!(kinds(&events), vec!["approaching"]);
+
+        let events = watcher.update(
+            &resp(&[(datetime!(2024-03-01 08:52 +8), SeatsAvailable)]),
+            datetime!(2024-03-01 08:52:10 +8),
+        );
+        assert_eq!(kinds(&events), vec!["arrived"]);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod passenger_vol;
+
+pub use passenger_vol::{VolDayType, YearMonth};
@@ -0,0 +1,232 @@
+//! Month selection for the passenger volume APIs
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::{Date, Month};
+
+use crate::utils::DayType;
+#[cfg(any(
+    feature = "reqwest-async",
+    feature = "reqwest-blocking",
+    feature = "ureq-blocking"
+))]
+use crate::{utils::today_sgt, LTAError, LTAResult};
+
+/// Number of months that DataMall keeps passenger volume files for
+const AVAILABLE_MONTHS: u8 = 3;
+
+/// Day of the month by which the previous month's data is generated
+const PUBLISH_DAY: u8 = 15;
+
+/// A calendar month, used to select which passenger volume file to request.
+///
+/// Formats as `YYYYMM`, which is what DataMall expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct YearMonth {
+    year: i32,
+    month: Month,
+}
+
+impl YearMonth {
+    pub fn new(year: i32, month: Month) -> Self {
+        YearMonth { year, month }
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> Month {
+        self.month
+    }
+
+    /// Returns the month before `self`
+    pub fn prev(self) -> Self {
+        match self.month {
+            Month::January => YearMonth::new(self.year - 1, Month::December),
+            m => YearMonth::new(self.year, m.previous()),
+        }
+    }
+
+    /// Returns the month after `self`
+    pub fn next(self) -> Self {
+        match self.month {
+            Month::December => YearMonth::new(self.year + 1, Month::January),
+            m => YearMonth::new(self.year, m.next()),
+        }
+    }
+
+    /// Returns `true` if DataMall serves passenger volume data for this month as of `today`
+    pub fn is_available(self, today: Date) -> bool {
+        available_months(today).contains(&self)
+    }
+}
+
+impl From<Date> for YearMonth {
+    fn from(date: Date) -> Self {
+        YearMonth::new(date.year(), date.month())
+    }
+}
+
+impl Ord for YearMonth {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.year, u8::from(self.month)).cmp(&(other.year, u8::from(other.month)))
+    }
+}
+
+impl PartialOrd for YearMonth {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for YearMonth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}{:02}", self.year, u8::from(self.month))
+    }
+}
+
+/// Error returned when a string is not a valid `YYYYMM` month
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid year-month `{0}`, expected `YYYYMM`")]
+pub struct ParseYearMonthError(String);
+
+impl FromStr for YearMonth {
+    type Err = ParseYearMonthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseYearMonthError(s.to_string());
+
+        if s.len() != 6 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+
+        let year = s[0..4].parse::<i32>().map_err(|_| err())?;
+        let month = s[4..6]
+            .parse::<u8>()
+            .ok()
+            .and_then(|m| Month::try_from(m).ok())
+            .ok_or_else(err)?;
+
+        Ok(YearMonth::new(year, month))
+    }
+}
+
+/// Returns the months that passenger volume data can be requested for as of `today`, latest first.
+///
+/// DataMall only serves the last three generated files. The previous month's file is generated
+/// by the 15th, so before that the window starts one month earlier.
+pub fn available_months(today: Date) -> Vec<YearMonth> {
+    let mut latest = YearMonth::from(today).prev();
+
+    if today.day() < PUBLISH_DAY {
+        latest = latest.prev();
+    }
+
+    let mut months = Vec::with_capacity(AVAILABLE_MONTHS as usize);
+    for _ in 0..AVAILABLE_MONTHS {
+        months.push(latest);
+        latest = latest.prev();
+    }
+
+    months
+}
+
+/// Value of the `DAY_TYPE` column of the passenger volume files, which groups Saturdays with
+/// Sundays and public holidays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VolDayType {
+    #[serde(rename = "WEEKDAY")]
+    Weekday,
+
+    #[serde(rename = "WEEKENDS/HOLIDAY")]
+    WeekendsOrHoliday,
+}
+
+impl VolDayType {
+    /// Day type that the trips made on `date` are counted under, see [`DayType::for_date`]
+    pub fn for_date(date: Date) -> Self {
+        DayType::for_date(date).into()
+    }
+}
+
+impl From<DayType> for VolDayType {
+    fn from(day_type: DayType) -> Self {
+        match day_type {
+            DayType::Weekday => VolDayType::Weekday,
+            DayType::Saturday | DayType::SundayOrHoliday => VolDayType::WeekendsOrHoliday,
+        }
+    }
+}
+
+/// Validates `month` against the current window and formats it for the `Date` query parameter
+#[cfg(any(
+    feature = "reqwest-async",
+    feature = "reqwest-blocking",
+    feature = "ureq-blocking"
+))]
+pub(crate) fn query_month(month: Option<YearMonth>) -> LTAResult<Option<String>> {
+    match month {
+        Some(m) if !m.is_available(today_sgt()) => Err(LTAError::UnavailableMonth(m)),
+        Some(m) => Ok(Some(m.to_string())),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn parse_and_format() {
+        let ym: YearMonth = "202403".parse().unwrap();
+        assert_eq!(ym, YearMonth::new(2024, Month::March));
+        assert_eq!(ym.to_string(), "202403");
+
+        assert!("2024-03".parse::<YearMonth>().is_err());
+        assert!("202413".parse::<YearMonth>().is_err());
+        assert!("202400".parse::<YearMonth>().is_err());
+        assert!("２０２４03".parse::<YearMonth>().is_err());
+        assert!("".parse::<YearMonth>().is_err());
+    }
+
+    #[test]
+    fn window_after_publish_day() {
+        let months = available_months(date!(2024 - 01 - 20));
+        assert_eq!(
+            months,
+            vec![
+                YearMonth::new(2023, Month::December),
+                YearMonth::new(2023, Month::November),
+                YearMonth::new(2023, Month::October),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_before_publish_day() {
+        let today = date!(2024 - 03 - 02);
+        let months = available_months(today);
+        assert_eq!(months[0], YearMonth::new(2024, Month::January));
+        assert_eq!(months[2], YearMonth::new(2023, Month::November));
+        assert!(!YearMonth::new(2024, Month::February).is_available(today));
+        assert!(!YearMonth::new(2024, Month::March).is_available(today));
+    }
+
+    #[test]
+    fn day_type_of_date() {
+        assert_eq!(
+            VolDayType::for_date(date!(2024 - 03 - 01)),
+            VolDayType::Weekday
+        );
+        assert_eq!(
+            VolDayType::for_date(date!(2024 - 03 - 29)),
+            VolDayType::WeekendsOrHoliday
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Decoding of the JSON files linked by the facilities maintenance API
+
+use serde::{Deserialize, Serialize};
+use time::macros::format_description;
+use time::Date;
+
+use crate::models::train::prelude::*;
+use crate::utils::today_sgt;
+use crate::{LTAError, LTAResult};
+
+/// Type of station facility that is under maintenance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum FacilityType {
+    Lift,
+    Escalator,
+
+    #[default]
+    Unknown,
+}
+
+impl FacilityType {
+    fn from_desc(desc: &str) -> Self {
+        let desc = desc.to_ascii_lowercase();
+        if desc.contains("escalator") {
+            FacilityType::Escalator
+        } else if desc.contains("lift") {
+            FacilityType::Lift
+        } else {
+            FacilityType::Unknown
+        }
+    }
+}
+
+/// A facility of a station that is under maintenance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", try_from = "RawFacilityMaintenance")]
+pub struct FacilityMaintenance {
+    pub line: MrtLine,
+    pub station_code: StationCode,
+    pub station_name: String,
+    pub facility_type: FacilityType,
+
+    /// Identifier of the facility within the station, eg `B1L01`
+    pub facility_id: String,
+
+    /// Description of where the facility is, eg `Exit A Street level - Concourse`
+    pub location: String,
+
+    /// `None` if the file does not include a start date
+    pub start_date: Option<Date>,
+
+    /// `None` if the file does not include an end date
+    pub end_date: Option<Date>,
+}
+
+impl FacilityMaintenance {
+    /// Decodes the contents of a facilities maintenance file
+    pub fn from_json(bytes: &[u8]) -> LTAResult<Vec<FacilityMaintenance>> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum File {
+            Wrapped { value: Vec<FacilityMaintenance> },
+            Bare(Vec<FacilityMaintenance>),
+        }
+
+        match serde_json::from_slice(bytes).map_err(|_| LTAError::FailedToParseBody)? {
+            File::Wrapped { value } | File::Bare(value) => Ok(value),
+        }
+    }
+
+    /// Returns `true` if the facility is under maintenance on `date`. Missing start or end
+    /// dates are treated as open ended, since the file only lists ongoing and upcoming works
+    pub fn is_under_maintenance(&self, date: Date) -> bool {
+        self.start_date.is_none_or(|start| start <= date)
+            && self.end_date.is_none_or(|end| date <= end)
+    }
+
+    /// Same as `is_under_maintenance`, for the current date in Singapore
+    pub fn is_under_maintenance_now(&self) -> bool {
+        self.is_under_maintenance(today_sgt())
+    }
+}
+
+/// Returns the records of `facility_type` that are under maintenance on `date`
+pub fn under_maintenance(
+    records: &[FacilityMaintenance],
+    facility_type: FacilityType,
+    date: Date,
+) -> impl Iterator<Item = &FacilityMaintenance> {
+    records
+        .iter()
+        .filter(move |r| r.facility_type == facility_type && r.is_under_maintenance(date))
+}
+
+/// Shape of the records in the file. Lift maintenance files only have `LiftID` and
+/// `LiftDesc`, so the facility type is inferred when it is not given
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawFacilityMaintenance {
+    #[serde(default)]
+    line: MrtLine,
+    station_code: StationCode,
+    #[serde(default)]
+    station_name: String,
+    #[serde(default)]
+    facility_type: Option<FacilityType>,
+    #[serde(rename = "LiftID", default)]
+    lift_id: Option<String>,
+    #[serde(alias = "FacilityID", default)]
+    facility_id: Option<String>,
+    #[serde(alias = "LiftDesc", alias = "FacilityDesc", default)]
+    location: String,
+    #[serde(default)]
+    start_date: Option<String>,
+    #[serde(default)]
+    end_date: Option<String>,
+}
+
+impl TryFrom<RawFacilityMaintenance> for FacilityMaintenance {
+    type Error = String;
+
+    fn try_from(raw: RawFacilityMaintenance) -> Result<Self, Self::Error> {
+        let facility_type = match (raw.facility_type, &raw.lift_id) {
+            (Some(t), _) if t != FacilityType::Unknown => t,
+            (_, Some(_)) => FacilityType::Lift,
+            _ => FacilityType::from_desc(&raw.location),
+        };
+
+        Ok(FacilityMaintenance {
+            line: raw.line,
+            station_code: raw.station_code,
+            station_name: raw.station_name,
+            facility_type,
+            facility_id: raw.lift_id.or(raw.facility_id).unwrap_or_default(),
+            location: raw.location,
+            start_date: raw.start_date.as_deref().map(parse_date).transpose()?,
+            end_date: raw.end_date.as_deref().map(parse_date).transpose()?,
+        })
+    }
+}
+
+/// Accepts `YYYY-MM-DD`, an ISO 8601 timestamp or `DD/MM/YYYY`
+fn parse_date(s: &str) -> Result<Date, String> {
+    let iso = format_description!("[year]-[month]-[day]");
+    let local = format_description!("[day]/[month]/[year]");
+    let s = s.trim();
+
+    s.get(..10)
+        .and_then(|d| Date::parse(d, iso).ok())
+        .or_else(|| Date::parse(s, local).ok())
+        .ok_or_else(|| format!("Invalid date `{}`", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    const LIFTS: &str = r#"{"value":[
+        {"Line":"NSL","StationCode":"NS1","StationName":"Jurong East","LiftID":"B1L01","LiftDesc":"Exit A Street level - Concourse"},
+        {"Line":"EWL","StationCode":"EW24","StationName":"Jurong East","FacilityType":"Escalator","FacilityID":"E02","Location":"Platform A - Concourse","StartDate":"2024-03-01","EndDate":"10/03/2024"}
+    ]}"#;
+
+    #[test]
+    fn decode_records() {
+        let records = FacilityMaintenance::from_json(LIFTS.as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].line, MrtLine::NSL);
+        assert_eq!(records[0].station_code, StationCode::NS1);
+        assert_eq!(records[0].facility_type, FacilityType::Lift);
+        assert_eq!(records[0].facility_id, "B1L01");
+        assert_eq!(records[0].start_date, None);
+
+        assert_eq!(records[1].facility_type, FacilityType::Escalator);
+        assert_eq!(records[1].start_date, Some(date!(2024 - 03 - 01)));
+        assert_eq!(records[1].end_date, Some(date!(2024 - 03 - 10)));
+
+        let bare = FacilityMaintenance::from_json(b"[]").unwrap();
+        assert!(bare.is_empty());
+
+        let invalid = r#"{"Line":"NSL","StationCode":"NS1","LiftID":"B1L01","StartDate":"soon"}"#;
+        let err = serde_json::from_str::<FacilityMaintenance>(invalid).unwrap_err();
+        assert!(err.to_string().contains("Invalid date `soon`"), "{}", err);
+        let file = format!(r#"{{"value":[{}]}}"#, invalid);
+        assert!(FacilityMaintenance::from_json(file.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn maintenance_window() {
+        let records = FacilityMaintenance::from_json(LIFTS.as_bytes()).unwrap();
+        let escalator = &records[1];
+
+        assert!(!escalator.is_under_maintenance(date!(2024 - 02 - 29)));
+        assert!(escalator.is_under_maintenance(date!(2024 - 03 - 01)));
+        assert!(escalator.is_under_maintenance(date!(2024 - 03 - 10)));
+        assert!(!escalator.is_under_maintenance(date!(2024 - 03 - 11)));
+
+        let lifts = under_maintenance(&records, FacilityType::Lift, date!(2024 - 03 - 11));
+        assert_eq!(lifts.count(), 1);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let records = FacilityMaintenance::from_json(LIFTS.as_bytes()).unwrap();
+        let json = serde_json::to_vec(&records).unwrap();
+        assert_eq!(FacilityMaintenance::from_json(&json).unwrap(), records);
+    }
+}
@@ -0,0 +1,157 @@
+//! GeoJSON features of the models that carry a location
+
+use geojson::{Feature, FeatureCollection, GeometryValue, JsonObject, JsonValue, Position};
+use serde::Serialize;
+
+use crate::models::bus::prelude::BusStop;
+use crate::models::taxi::prelude::TaxiStand;
+use crate::models::traffic::prelude::{
+    BikeParking, CarPark, FaultyTrafficLight, TrafficImage, TrafficIncident, TrafficSpeedBand,
+};
+use crate::models::utils::Coordinates;
+
+fn point(lat: f64, long: f64) -> Option<geojson::Geometry> {
+    Some(geojson::Geometry::new(GeometryValue::new_point(
+        Position::from([long, lat]),
+    )))
+}
+
+/// A model that can be drawn on a map. Every field of the model becomes a property of its
+/// feature
+pub trait ToGeoJson: Serialize {
+    /// Geometry in WGS84, `None` if the model has no location
+    fn geometry(&self) -> Option<geojson::Geometry>;
+
+    fn to_geojson(&self) -> Feature {
+        let properties = match serde_json::to_value(self) {
+            Ok(JsonValue::Object(properties)) => properties,
+            Ok(value) => JsonObject::from_iter([("value".to_string(), value)]),
+            Err(_) => JsonObject::new(),
+        };
+
+        Feature {
+            geometry: self.geometry(),
+            properties: Some(properties),
+            ..Default::default()
+        }
+    }
+}
+
+/// Collections of [`ToGeoJson`] models
+pub trait ToFeatureCollection {
+    fn to_geojson(&self) -> FeatureCollection;
+}
+
+impl<T: ToGeoJson> ToFeatureCollection for [T] {
+    fn to_geojson(&self) -> FeatureCollection {
+        FeatureCollection::new(self.iter().map(ToGeoJson::to_geojson))
+    }
+}
+
+macro_rules! impl_point {
+    ($($model:ty),+) => {
+        $(
+            impl ToGeoJson for $model {
+                fn geometry(&self) -> Option<geojson::Geometry> {
+                    point(self.lat, self.long)
+                }
+            }
+        )+
+    };
+}
+
+impl_point!(
+    Coordinates,
+    BusStop,
+    TaxiStand,
+    BikeParking,
+    TrafficIncident,
+    TrafficImage
+);
+
+impl ToGeoJson for CarPark {
+    fn geometry(&self) -> Option<geojson::Geometry> {
+        let c = self.coords.as_ref()?;
+        point(c.lat, c.long)
+    }
+}
+
+/// Faulty traffic lights are only identified by their node, so their features have no
+/// geometry
+impl ToGeoJson for FaultyTrafficLight {
+    fn geometry(&self) -> Option<geojson::Geometry> {
+        None
+    }
+}
+
+/// A line from the start to the end of the link
+impl ToGeoJson for TrafficSpeedBand {
+    fn geometry(&self) -> Option<geojson::Geometry> {
+        Some(geojson::Geometry::new(GeometryValue::new_line_string([
+            Position::from([self.start_lon, self.start_lat]),
+            Position::from([self.end_lon, self.end_lat]),
+        ])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::traffic::prelude::RoadCategory;
+
+    #[test]
+    fn points_with_properties() {
+        let stops = [BusStop {
+            bus_stop_code: 83139,
+            road_name: "Upp Changi Rd".to_string(),
+            desc: "Opp Blk 1".to_string(),
+            lat: 1.3167,
+            long: 103.9055,
+        }];
+        let collection = stops.to_geojson();
+        assert_eq!(collection.features.len(), 1);
+
+        let feature = &collection.features[0];
+        assert_eq!(
+            feature.geometry.as_ref().unwrap().value,
+            GeometryValue::new_point(Position::from([103.9055, 1.3167]))
+        );
+        assert_eq!(
+            feature.property("bus_stop_code"),
+            Some(&JsonValue::from(83139))
+        );
+
+        let taxis = [Coordinates::new(1.3, 103.8)];
+        let json = taxis.to_geojson().to_string();
+        assert!(json.contains("[103.8,1.3]"), "{}", json);
+    }
+
+    #[test]
+    fn speed_band_line() {
+        let band = TrafficSpeedBand {
+            link_id: 103000000,
+            road_name: "KENT ROAD".to_string(),
+            road_category: RoadCategory::SlipRoads,
+            speed_band: 4,
+            min_speed: 30,
+            max_speed: 39,
+            start_lon: 103.85,
+            start_lat: 1.31,
+            end_lon: 103.86,
+            end_lat: 1.32,
+        };
+
+        let feature = band.to_geojson();
+        assert_eq!(
+            feature.geometry.unwrap().value,
+            GeometryValue::new_line_string([
+                Position::from([103.85, 1.31]),
+                Position::from([103.86, 1.32]),
+            ])
+        );
+        assert_eq!(
+            feature.properties.unwrap()["speed_band"],
+            JsonValue::from(4)
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! Decoding of the zipped shapefiles returned by the geospatial whole island API
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read};
+
+use serde::{Deserialize, Serialize};
+use shapefile::dbase::{self, FieldValue};
+use shapefile::{PolygonRing, Shape, ShapeReader};
+use time::{Date, Month};
+use zip::ZipArchive;
+
+use crate::geo::Svy21;
+use crate::{LTAError, LTAResult};
+
+/// Upper bound on the buffer reserved from the size a zip entry claims, larger entries grow
+/// as they are read
+const MAX_PREALLOC: u64 = 64 * 1024 * 1024;
+
+/// Point in the coordinate system of the layer. For DataMall layers this is SVY21, where `x` is
+/// the easting and `y` is the northing in metres
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+}
+
+impl From<Point> for Svy21 {
+    fn from(p: Point) -> Self {
+        Svy21::new(p.y, p.x)
+    }
+}
+
+impl From<Svy21> for Point {
+    fn from(svy21: Svy21) -> Self {
+        Point::new(svy21.easting, svy21.northing)
+    }
+}
+
+/// Ring of a polygon. Outer rings are followed by the holes that they contain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ring {
+    pub is_outer: bool,
+    pub points: Vec<Point>,
+}
+
+/// Geometry of a single feature. Z and M values are dropped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Geometry {
+    Point(Point),
+    MultiPoint(Vec<Point>),
+    /// One or more parts
+    Polyline(Vec<Vec<Point>>),
+    Polygon(Vec<Ring>),
+    Null,
+}
+
+/// Value of a column in the `.dbf` attribute table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AttributeValue {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    Date(Date),
+    Null,
+}
+
+/// A geometry with its attributes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeospatialFeature {
+    pub geometry: Geometry,
+    pub attributes: BTreeMap<String, AttributeValue>,
+}
+
+/// A decoded shapefile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeospatialLayer {
+    /// Name of the shapefile within the archive, without extension
+    pub name: String,
+
+    /// Well-known text of the coordinate system from the `.prj` file, if present
+    pub projection: Option<String>,
+
+    pub features: Vec<GeospatialFeature>,
+}
+
+impl GeospatialLayer {
+    /// Decodes every shapefile contained in a zip archive
+    pub fn from_zip(bytes: &[u8]) -> LTAResult<Vec<GeospatialLayer>> {
+        let mut archive =
+            ZipArchive::new(Cursor::new(bytes)).map_err(|_| LTAError::FailedToParseBody)?;
+
+        // stem -> extension -> contents
+        let mut files: BTreeMap<String, HashMap<String, Vec<u8>>> = BTreeMap::new();
+
+        for i in 0..archive.len() {
+            let mut file = archive
+                .by_index(i)
+                .map_err(|_| LTAError::FailedToParseBody)?;
+
+            if file.is_dir() {
+                continue;
+            }
+
+            let Some((stem, ext)) = file.name().rsplit_once('.') else {
+                continue;
+            };
+
+            let ext = ext.to_ascii_lowercase();
+            if !matches!(ext.as_str(), "shp" | "dbf" | "prj") {
+                continue;
+            }
+
+            let stem = stem.to_string();
+            let mut buf = Vec::with_capacity(file.size().min(MAX_PREALLOC) as usize);
+            file.read_to_end(&mut buf)
+                .map_err(|_| LTAError::FailedToParseBody)?;
+
+            files.entry(stem).or_default().insert(ext, buf);
+        }
+
+        let layers = files
+            .into_iter()
+            .filter(|(_, parts)| parts.contains_key("shp"))
+            .map(|(stem, parts)| Self::from_parts(&stem, parts))
+            .collect::<LTAResult<Vec<_>>>()?;
+
+        if layers.is_empty() {
+            return Err(LTAError::FailedToParseBody);
+        }
+
+        Ok(layers)
+    }
+
+    fn from_parts(stem: &str, mut parts: HashMap<String, Vec<u8>>) -> LTAResult<Self> {
+        let shp = parts.remove("shp").unwrap_or_default();
+        let shapes = ShapeReader::new(Cursor::new(shp))
+            .and_then(|r| r.read())
+            .map_err(|_| LTAError::FailedToParseBody)?;
+
+        let mut records = match parts.remove("dbf") {
+            Some(dbf) => dbase::Reader::new(Cursor::new(dbf))
+                .and_then(|mut r| r.read())
+                .map_err(|_| LTAError::FailedToParseBody)?
+                .into_iter()
+                .map(|r| {
+                    r.into_iter()
+                        .map(|(k, v)| (k, AttributeValue::from(v)))
+                        .collect()
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if !records.is_empty() && records.len() != shapes.len() {
+            return Err(LTAError::FailedToParseBody);
+        }
+        records.resize_with(shapes.len(), BTreeMap::new);
+
+        let features = shapes
+            .into_iter()
+            .zip(records)
+            .map(|(shape, attributes)| GeospatialFeature {
+                geometry: shape.into(),
+                attributes,
+            })
+            .collect();
+
+        let projection = parts
+            .remove("prj")
+            .map(|prj| String::from_utf8_lossy(&prj).trim().to_string());
+
+        let name = stem.rsplit('/').next().unwrap_or(stem).to_string();
+
+        Ok(GeospatialLayer {
+            name,
+            projection,
+            features,
+        })
+    }
+
+    /// Reprojects the layer from SVY21 to WGS84, where `x` is the longitude and `y` is the
+    /// latitude. Assumes the layer is in SVY21, which is the case for all DataMall layers
+    pub fn to_wgs84(&self) -> GeospatialLayer {
+        let to_wgs84 = |p: &Point| {
+            let coords = Svy21::from(*p).to_wgs84();
+            Point::new(coords.long, coords.lat)
+        };
+
+        GeospatialLayer {
+            name: self.name.clone(),
+            projection: Some(WGS84_WKT.to_string()),
+            features: self
+                .features
+                .iter()
+                .map(|f| GeospatialFeature {
+                    geometry: f.geometry.map_points(to_wgs84),
+                    attributes: f.attributes.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Well-known text of WGS84, set as the projection of reprojected layers
+const WGS84_WKT: &str = r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#;
+
+impl Geometry {
+    /// Returns a copy of the geometry with `f` applied to every point
+    pub fn map_points(&self, f: impl Fn(&Point) -> Point) -> Geometry {
+        let line = |points: &[Point]| points.iter().map(&f).collect::<Vec<_>>();
+
+        match self {
+            Geometry::Point(p) => Geometry::Point(f(p)),
+            Geometry::MultiPoint(points) => Geometry::MultiPoint(line(points)),
+            Geometry::Polyline(parts) => {
+                Geometry::Polyline(parts.iter().map(|p| line(p)).collect())
+            }
+            Geometry::Polygon(rings) => Geometry::Polygon(
+                rings
+                    .iter()
+                    .map(|r| Ring {
+                        is_outer: r.is_outer,
+                        points: line(&r.points),
+                    })
+                    .collect(),
+            ),
+            Geometry::Null => Geometry::Null,
+        }
+    }
+}
+
+macro_rules! points {
+    ($points: expr) => {
+        $points.iter().map(|p| Point::new(p.x, p.y)).collect()
+    };
+}
+
+macro_rules! rings {
+    ($rings: expr) => {
+        $rings
+            .iter()
+            .map(|r| Ring {
+                is_outer: matches!(r, PolygonRing::Outer(_)),
+                points: points!(r.points()),
+            })
+            .collect()
+    };
+}
+
+impl From<Shape> for Geometry {
+    fn from(shape: Shape) -> Self {
+        match shape {
+            Shape::Point(p) => Geometry::Point(Point::new(p.x, p.y)),
+            Shape::PointM(p) => Geometry::Point(Point::new(p.x, p.y)),
+            Shape::PointZ(p) => Geometry::Point(Point::new(p.x, p.y)),
+            Shape::Multipoint(m) => Geometry::MultiPoint(points!(m.points())),
+            Shape::MultipointM(m) => Geometry::MultiPoint(points!(m.points())),
+            Shape::MultipointZ(m) => Geometry::MultiPoint(points!(m.points())),
+            Shape::Polyline(l) => {
+                Geometry::Polyline(l.parts().iter().map(|p| points!(p)).collect())
+            }
+            Shape::PolylineM(l) => {
+                Geometry::Polyline(l.parts().iter().map(|p| points!(p)).collect())
+            }
+            Shape::PolylineZ(l) => {
+                Geometry::Polyline(l.parts().iter().map(|p| points!(p)).collect())
+            }
+            Shape::Polygon(p) => Geometry::Polygon(rings!(p.rings())),
+            Shape::PolygonM(p) => Geometry::Polygon(rings!(p.rings())),
+            Shape::PolygonZ(p) => Geometry::Polygon(rings!(p.rings())),
+            Shape::NullShape | Shape::Multipatch(_) => Geometry::Null,
+        }
+    }
+}
+
+fn to_date(date: dbase::Date) -> AttributeValue {
+    Month::try_from(date.month() as u8)
+        .ok()
+        .and_then(|m| Date::from_calendar_date(date.year() as i32, m, date.day() as u8).ok())
+        .map_or(AttributeValue::Null, AttributeValue::Date)
+}
+
+impl From<FieldValue> for AttributeValue {
+    fn from(value: FieldValue) -> Self {
+        match value {
+            FieldValue::Character(Some(s)) | FieldValue::Memo(s) => {
+                AttributeValue::Text(s.trim_end().to_string())
+            }
+            FieldValue::Numeric(Some(n)) | FieldValue::Double(n) | FieldValue::Currency(n) => {
+                AttributeValue::Number(n)
+            }
+            FieldValue::Float(Some(n)) => AttributeValue::Number(n as f64),
+            FieldValue::Integer(n) => AttributeValue::Number(n as f64),
+            FieldValue::Logical(Some(b)) => AttributeValue::Bool(b),
+            FieldValue::Date(Some(d)) => to_date(d),
+            FieldValue::DateTime(dt) => to_date(dt.date()),
+            FieldValue::Character(None)
+            | FieldValue::Numeric(None)
+            | FieldValue::Float(None)
+            | FieldValue::Logical(None)
+            | FieldValue::Date(None) => AttributeValue::Null,
+        }
+    }
+}
+
+#[cfg(feature = "geojson")]
+mod to_geojson {
+    use super::*;
+    use geojson::{Feature, FeatureCollection, GeometryValue, JsonObject, JsonValue, Position};
+
+    fn position(p: &Point) -> Position {
+        Position::from([p.x, p.y])
+    }
+
+    fn line(points: &[Point]) -> Vec<Position> {
+        points.iter().map(position).collect()
+    }
+
+    impl Geometry {
+        /// Returns `None` for [`Geometry::Null`]
+        pub fn to_geojson(&self) -> Option<geojson::Geometry> {
+            let value = match self {
+                Geometry::Point(p) => GeometryValue::new_point(position(p)),
+                Geometry::MultiPoint(points) => GeometryValue::new_multi_point(line(points)),
+                Geometry::Polyline(parts) if parts.len() == 1 => {
+                    GeometryValue::new_line_string(line(&parts[0]))
+                }
+                Geometry::Polyline(parts) => {
+                    GeometryValue::new_multi_line_string(parts.iter().map(|p| line(p)))
+                }
+                Geometry::Polygon(rings) => {
+                    // Each outer ring starts a new polygon, the inner rings after it are its holes
+                    let mut polygons: Vec<Vec<Vec<Position>>> = Vec::new();
+                    for ring in rings {
+                        match polygons.last_mut() {
+                            Some(polygon) if !ring.is_outer => polygon.push(line(&ring.points)),
+                            _ => polygons.push(vec![line(&ring.points)]),
+                        }
+                    }
+
+                    if polygons.len() == 1 {
+                        GeometryValue::new_polygon(polygons.remove(0))
+                    } else {
+                        GeometryValue::new_multi_polygon(polygons)
+                    }
+                }
+                Geometry::Null => return None,
+            };
+
+            Some(geojson::Geometry::new(value))
+        }
+    }
+
+    impl From<&AttributeValue> for JsonValue {
+        fn from(value: &AttributeValue) -> Self {
+            match value {
+                AttributeValue::Text(s) => JsonValue::from(s.as_str()),
+                AttributeValue::Number(n) => JsonValue::from(*n),
+                AttributeValue::Bool(b) => JsonValue::from(*b),
+                AttributeValue::Date(d) => JsonValue::from(d.to_string()),
+                AttributeValue::Null => JsonValue::Null,
+            }
+        }
+    }
+
+    impl GeospatialFeature {
+        pub fn to_geojson(&self) -> Feature {
+            let properties: JsonObject = self
+                .attributes
+                .iter()
+                .map(|(k, v)| (k.clone(), JsonValue::from(v)))
+                .collect();
+
+            Feature {
+                geometry: self.geometry.to_geojson(),
+                properties: Some(properties),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl GeospatialLayer {
+        /// Converts the layer to a GeoJSON `FeatureCollection`. Coordinates are kept in the
+        /// coordinate system of the layer
+        pub fn to_geojson(&self) -> FeatureCollection {
+            FeatureCollection::new(self.features.iter().map(GeospatialFeature::to_geojson))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shapefile::dbase::{FieldName, Record, TableWriterBuilder};
+    use shapefile::{PolygonRing, ShapeWriter};
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn zipped_layer() -> Vec<u8> {
+        let mut shp = Cursor::new(Vec::new());
+        let polygons = vec![shapefile::Polygon::with_rings(vec![
+            PolygonRing::Outer(vec![
+                shapefile::Point::new(0.0, 0.0),
+                shapefile::Point::new(0.0, 10.0),
+                shapefile::Point::new(10.0, 10.0),
+                shapefile::Point::new(10.0, 0.0),
+                shapefile::Point::new(0.0, 0.0),
+            ]),
+            PolygonRing::Inner(vec![
+                shapefile::Point::new(2.0, 2.0),
+                shapefile::Point::new(4.0, 2.0),
+                shapefile::Point::new(4.0, 4.0),
+                shapefile::Point::new(2.0, 4.0),
+                shapefile::Point::new(2.0, 2.0),
+            ]),
+        ])];
+        ShapeWriter::new(&mut shp).write_shapes(&polygons).unwrap();
+
+        let mut dbf = Cursor::new(Vec::new());
+        let mut record = Record::default();
+        record.insert(
+            "TYP_CD".to_string(),
+            FieldValue::Character(Some("OVERHEAD".to_string())),
+        );
+        record.insert("LVL".to_string(), FieldValue::Numeric(Some(2.0)));
+        TableWriterBuilder::new()
+            .add_character_field(FieldName::try_from("TYP_CD").unwrap(), 20)
+            .add_numeric_field(FieldName::try_from("LVL").unwrap(), 10, 0)
+            .build_with_dest(&mut dbf)
+            .write_records(&[record])
+            .unwrap();
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        for (name, data) in [
+            ("Bridge/Bridge.shp", shp.into_inner()),
+            ("Bridge/Bridge.dbf", dbf.into_inner()),
+            ("Bridge/Bridge.prj", b"PROJCS[\"SVY21\"]".to_vec()),
+            ("Bridge/readme.txt", b"ignored".to_vec()),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&data).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn decode_zipped_shapefile() {
+        let layers = GeospatialLayer::from_zip(&zipped_layer()).unwrap();
+        assert_eq!(layers.len(), 1);
+
+        let layer = &layers[0];
+        assert_eq!(layer.name, "Bridge");
+        assert_eq!(layer.projection.as_deref(), Some("PROJCS[\"SVY21\"]"));
+        assert_eq!(layer.features.len(), 1);
+
+        let feature = &layer.features[0];
+        assert_eq!(
+            feature.attributes.get("TYP_CD"),
+            Some(&AttributeValue::Text("OVERHEAD".to_string()))
+        );
+        assert_eq!(
+            feature.attributes.get("LVL"),
+            Some(&AttributeValue::Number(2.0))
+        );
+
+        match &feature.geometry {
+            Geometry::Polygon(rings) => {
+                assert_eq!(rings.len(), 2);
+                assert!(rings[0].is_outer);
+                assert!(!rings[1].is_outer);
+            }
+            g => panic!("Unexpected geometry {:?}", g),
+        }
+    }
+
+    #[test]
+    fn reproject_to_wgs84() {
+        let layers = GeospatialLayer::from_zip(&zipped_layer()).unwrap();
+        let layer = layers[0].to_wgs84();
+
+        let Geometry::Polygon(rings) = &layer.features[0].geometry else {
+            panic!("Unexpected geometry {:?}", layer.features[0].geometry);
+        };
+
+        for p in rings.iter().flat_map(|r| &r.points) {
+            assert!((103.5..104.5).contains(&p.x), "{:?}", p);
+            assert!((1.0..1.6).contains(&p.y), "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn invalid_archive() {
+        assert!(GeospatialLayer::from_zip(b"not a zip").is_err());
+    }
+
+    #[cfg(feature = "geojson")]
+    #[test]
+    fn layer_to_geojson() {
+        let layers = GeospatialLayer::from_zip(&zipped_layer()).unwrap();
+        let collection = layers[0].to_geojson();
+        let feature = &collection.features[0];
+
+        match &feature.geometry.as_ref().unwrap().value {
+            geojson::GeometryValue::Polygon { coordinates } => assert_eq!(coordinates.len(), 2),
+            g => panic!("Unexpected geometry {:?}", g),
+        }
+        assert_eq!(
+            feature.properties.as_ref().unwrap()["TYP_CD"],
+            geojson::JsonValue::from("OVERHEAD")
+        );
+    }
+}
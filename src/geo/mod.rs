@@ -0,0 +1,13 @@
+#[cfg(feature = "geojson")]
+pub mod feature;
+#[cfg(feature = "shapefile")]
+pub mod layer;
+pub mod spatial;
+pub mod svy21;
+
+#[cfg(feature = "geojson")]
+pub use feature::{ToFeatureCollection, ToGeoJson};
+#[cfg(feature = "shapefile")]
+pub use layer::{AttributeValue, Geometry, GeospatialFeature, GeospatialLayer, Point, Ring};
+pub use spatial::{haversine, Located, Neighbour, SpatialIndex};
+pub use svy21::Svy21;
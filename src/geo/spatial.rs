@@ -0,0 +1,418 @@
+//! Grid based spatial index for nearest neighbour, radius and bounding box queries
+
+use std::collections::HashMap;
+
+use crate::models::utils::Coordinates;
+
+/// Mean radius of the earth, in metres
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Metres per degree of latitude
+const METRES_PER_DEG: f64 = 111_320.0;
+
+/// Default side of a grid cell, in metres
+pub const DEFAULT_CELL_SIZE: f64 = 250.0;
+
+/// Smallest side of a grid cell, in metres. Smaller cells would not fit the cell coordinates
+/// of the whole globe in an `i32`
+pub const MIN_CELL_SIZE: f64 = 1.0;
+
+/// Great circle distance between two WGS84 coordinates, in metres
+pub fn haversine(a: &Coordinates, b: &Coordinates) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_long = (b.long - a.long).to_radians();
+
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_long / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// Anything with a WGS84 position
+pub trait Located {
+    fn coordinates(&self) -> Coordinates;
+}
+
+impl Located for Coordinates {
+    fn coordinates(&self) -> Coordinates {
+        self.clone()
+    }
+}
+
+/// An item of the index with its distance from the query point
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbour<'a, T> {
+    pub item: &'a T,
+
+    /// Haversine distance, in metres
+    pub distance: f64,
+}
+
+/// Index over items spread across a city sized area. Items are bucketed into square cells of
+/// an equirectangular projection, so queries only look at the cells around the query point
+#[derive(Debug, Clone)]
+pub struct SpatialIndex<T> {
+    items: Vec<T>,
+    positions: Vec<Coordinates>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    cell_size: f64,
+    cos_lat: f64,
+
+    /// Corners of the occupied cells
+    bounds: ((i32, i32), (i32, i32)),
+}
+
+impl<T: Located> SpatialIndex<T> {
+    /// Builds the index with cells of [`DEFAULT_CELL_SIZE`]
+    pub fn new(items: Vec<T>) -> Self {
+        Self::with_cell_size(items, DEFAULT_CELL_SIZE)
+    }
+
+    /// Builds the index with cells of `cell_size` metres, at least [`MIN_CELL_SIZE`]. A size
+    /// that is not a positive number falls back to [`DEFAULT_CELL_SIZE`]
+    pub fn with_cell_size(items: Vec<T>, cell_size: f64) -> Self {
+        let cell_size = if cell_size > 0.0 && cell_size.is_finite() {
+            cell_size.max(MIN_CELL_SIZE)
+        } else {
+            DEFAULT_CELL_SIZE
+        };
+        let positions = items.iter().map(Located::coordinates).collect::<Vec<_>>();
+        let mean_lat = match positions.len() {
+            0 => 0.0,
+            n => positions.iter().map(|c| c.lat).sum::<f64>() / n as f64,
+        };
+
+        let mut index = SpatialIndex {
+            items,
+            positions: Vec::new(),
+            cells: HashMap::new(),
+            cell_size,
+            cos_lat: mean_lat.to_radians().cos(),
+            bounds: ((0, 0), (0, 0)),
+        };
+
+        for (i, c) in positions.iter().enumerate() {
+            let cell = index.cell_of(c);
+            index.cells.entry(cell).or_default().push(i);
+        }
+
+        if !index.cells.is_empty() {
+            index.bounds = index.cells.keys().fold(
+                ((i32::MAX, i32::MAX), (i32::MIN, i32::MIN)),
+                |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+            );
+        }
+        index.positions = positions;
+        index
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the `k` items closest to `center`, closest first
+    pub fn nearest(&self, center: &Coordinates, k: usize) -> Vec<Neighbour<'_, T>> {
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+
+        let origin = self.cell_of(center);
+        if !self.contains_cell(origin) {
+            return self.scan(center, k, f64::INFINITY);
+        }
+
+        let mut found = Vec::new();
+
+        // Cells outside ring `r` are more than `r * cell_size` away from `center`, so once the
+        // k-th closest item is within that distance, no unvisited cell can hold a closer one
+        for r in 0..=self.rings_to_cover(origin) {
+            for i in self.ring(origin, r) {
+                found.push(self.neighbour(i, center));
+            }
+
+            if found.len() >= k {
+                sort_by_distance(&mut found);
+                // 1% slack for the difference between the grid and haversine distances
+                if found[k - 1].distance <= r as f64 * self.cell_size * 0.99 {
+                    break;
+                }
+            }
+        }
+
+        sort_by_distance(&mut found);
+        found.truncate(k);
+        found
+    }
+
+    /// Returns the items within `radius` metres of `center`, closest first
+    pub fn within(&self, center: &Coordinates, radius: f64) -> Vec<Neighbour<'_, T>> {
+        let origin = self.cell_of(center);
+        let rings = (radius / self.cell_size).ceil() + 1.0;
+
+        // Far from the items or with a radius that covers all of them, the grid only adds work
+        if !self.contains_cell(origin) || rings >= self.rings_to_cover(origin) as f64 {
+            return self.scan(center, usize::MAX, radius);
+        }
+
+        let mut found = (0..=rings as i32)
+            .flat_map(|r| self.ring(origin, r))
+            .map(|i| self.neighbour(i, center))
+            .filter(|n| n.distance <= radius)
+            .collect::<Vec<_>>();
+
+        sort_by_distance(&mut found);
+        found
+    }
+
+    /// Returns the items inside the box spanned by two opposite corners
+    pub fn within_bbox(&self, a: &Coordinates, b: &Coordinates) -> Vec<&T> {
+        let (min_lat, max_lat) = (a.lat.min(b.lat), a.lat.max(b.lat));
+        let (min_long, max_long) = (a.long.min(b.long), a.long.max(b.long));
+        let (x0, y0) = self.cell_of(&Coordinates::new(min_lat, min_long));
+        let (x1, y1) = self.cell_of(&Coordinates::new(max_lat, max_long));
+
+        // Corners far outside the projection saturate, so the span may not fit an `i32`
+        let span = u64::from(x1.abs_diff(x0)).saturating_mul(u64::from(y1.abs_diff(y0)));
+
+        let mut found = Vec::new();
+        if span > self.cells.len() as u64 {
+            // Box is larger than the occupied area, scanning the cells is cheaper
+            found.extend(self.cells.values().flatten().copied());
+        } else {
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    found.extend(self.cells.get(&(x, y)).into_iter().flatten().copied());
+                }
+            }
+        }
+
+        found.sort_unstable();
+        found
+            .into_iter()
+            .filter(|&i| {
+                let c = &self.positions[i];
+                (min_lat..=max_lat).contains(&c.lat) && (min_long..=max_long).contains(&c.long)
+            })
+            .map(|i| &self.items[i])
+            .collect()
+    }
+
+    fn cell_of(&self, c: &Coordinates) -> (i32, i32) {
+        let x = c.long * METRES_PER_DEG * self.cos_lat / self.cell_size;
+        let y = c.lat * METRES_PER_DEG / self.cell_size;
+        (x.floor() as i32, y.floor() as i32)
+    }
+
+    /// Whether `cell` is within the corners of the occupied cells
+    fn contains_cell(&self, (x, y): (i32, i32)) -> bool {
+        let ((x0, y0), (x1, y1)) = self.bounds;
+        (x0..=x1).contains(&x) && (y0..=y1).contains(&y)
+    }
+
+    /// Number of rings around `origin` that cover every occupied cell
+    fn rings_to_cover(&self, (ox, oy): (i32, i32)) -> i32 {
+        let ((x0, y0), (x1, y1)) = self.bounds;
+        [
+            ox.abs_diff(x0),
+            x1.abs_diff(ox),
+            oy.abs_diff(y0),
+            y1.abs_diff(oy),
+        ]
+        .into_iter()
+        .fold(0, |acc, d| acc.max(d.min(i32::MAX as u32) as i32))
+    }
+
+    /// Up to `k` items within `radius` metres of `center`, closest first, checking every item
+    fn scan(&self, center: &Coordinates, k: usize, radius: f64) -> Vec<Neighbour<'_, T>> {
+        let mut found = (0..self.len())
+            .map(|i| self.neighbour(i, center))
+            .filter(|n| n.distance <= radius)
+            .collect::<Vec<_>>();
+
+        sort_by_distance(&mut found);
+        found.truncate(k);
+        found
+    }
+
+    fn neighbour(&self, i: usize, center: &Coordinates) -> Neighbour<'_, T> {
+        Neighbour {
+            item: &self.items[i],
+            distance: haversine(center, &self.positions[i]),
+        }
+    }
+
+    /// Indices of the items in the cells exactly `r` cells away from `origin`. Only the cells
+    /// of the ring within the occupied corners are looked up
+    fn ring(&self, (ox, oy): (i32, i32), r: i32) -> impl Iterator<Item = usize> + '_ {
+        let ((x0, y0), (x1, y1)) = self.bounds;
+        let clamp_x = |x: i32| x.clamp(x0, x1);
+        let clamp_y = |y: i32| y.clamp(y0, y1);
+
+        let mut cells = Vec::new();
+        if r == 0 {
+            cells.push((ox, oy));
+        } else {
+            for y in [oy.checked_sub(r), oy.checked_add(r)].into_iter().flatten() {
+                if (y0..=y1).contains(&y) {
+                    let (from, to) = (ox.saturating_sub(r), ox.saturating_add(r));
+                    cells.extend((clamp_x(from)..=clamp_x(to)).map(|x| (x, y)));
+                }
+            }
+            for x in [ox.checked_sub(r), ox.checked_add(r)].into_iter().flatten() {
+                if (x0..=x1).contains(&x) {
+                    let (from, to) = (oy.saturating_sub(r - 1), oy.saturating_add(r - 1));
+                    cells.extend((clamp_y(from)..=clamp_y(to)).map(|y| (x, y)));
+                }
+            }
+        }
+
+        cells
+            .into_iter()
+            .filter_map(|c| self.cells.get(&c))
+            .flatten()
+            .copied()
+    }
+}
+
+fn sort_by_distance<T>(found: &mut [Neighbour<'_, T>]) {
+    found.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> SpatialIndex<Coordinates> {
+        // 20 x 20 points, roughly 111m apart, around the city centre
+        let points = (0..400)
+            .map(|i| {
+                Coordinates::new(
+                    1.28 + (i / 20) as f64 * 0.001,
+                    103.84 + (i % 20) as f64 * 0.001,
+                )
+            })
+            .collect();
+        SpatialIndex::new(points)
+    }
+
+    fn brute_force(index: &SpatialIndex<Coordinates>, center: &Coordinates) -> Vec<f64> {
+        let mut d = index
+            .items()
+            .iter()
+            .map(|c| haversine(center, c))
+            .collect::<Vec<_>>();
+        d.sort_by(f64::total_cmp);
+        d
+    }
+
+    #[test]
+    fn haversine_distance() {
+        // Raffles Place to Changi Airport, about 17.9km
+        let d = haversine(
+            &Coordinates::new(1.2840, 103.8514),
+            &Coordinates::new(1.3644, 103.9915),
+        );
+        assert!((d - 17_930.0).abs() < 200.0, "{}", d);
+        assert_eq!(
+            haversine(&Coordinates::new(1.3, 103.8), &Coordinates::new(1.3, 103.8)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let index = grid();
+        for center in [
+            Coordinates::new(1.2855, 103.8471),
+            Coordinates::new(1.25, 103.80),
+            Coordinates::new(1.35, 103.95),
+        ] {
+            let expected = brute_force(&index, &center);
+            let found = index.nearest(&center, 7);
+            assert_eq!(found.len(), 7);
+            for (n, d) in found.iter().zip(&expected) {
+                assert!((n.distance - d).abs() < 1e-6);
+            }
+        }
+
+        assert_eq!(
+            index.nearest(&Coordinates::new(1.3, 103.85), 1000).len(),
+            400
+        );
+    }
+
+    #[test]
+    fn radius_and_bbox() {
+        let index = grid();
+        let center = Coordinates::new(1.29, 103.85);
+
+        let expected = brute_force(&index, &center)
+            .into_iter()
+            .filter(|&d| d <= 300.0)
+            .count();
+        let found = index.within(&center, 300.0);
+        assert_eq!(found.len(), expected);
+        assert!(found.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+        let in_box = index.within_bbox(
+            &Coordinates::new(1.2805, 103.8405),
+            &Coordinates::new(1.2825, 103.8435),
+        );
+        assert_eq!(in_box.len(), 6);
+    }
+
+    #[test]
+    fn far_centre_and_huge_radius() {
+        let index = SpatialIndex::new(vec![
+            Coordinates::new(1.2840, 103.8514),
+            Coordinates::new(1.3644, 103.9915),
+        ]);
+        let far = Coordinates::new(0.0, 0.0);
+
+        let nearest = index.nearest(&far, 1);
+        assert_eq!(nearest.len(), 1);
+        assert_eq!(nearest[0].item, &Coordinates::new(1.2840, 103.8514));
+
+        assert_eq!(index.within(&far, 5_000_000.0).len(), 0);
+        assert_eq!(index.within(&far, f64::INFINITY).len(), 2);
+        assert_eq!(
+            index
+                .within(&Coordinates::new(1.3, 103.9), f64::INFINITY)
+                .len(),
+            2
+        );
+        assert_eq!(index.within(&far, f64::NAN).len(), 0);
+    }
+
+    #[test]
+    fn invalid_cell_size_and_infinite_box() {
+        let points = grid().items().to_vec();
+        for cell_size in [0.0, -250.0, f64::NAN, f64::INFINITY] {
+            let index = SpatialIndex::with_cell_size(points.clone(), cell_size);
+            assert_eq!(index.cell_size, DEFAULT_CELL_SIZE);
+        }
+
+        let tiny = SpatialIndex::with_cell_size(points, 1e-12);
+        assert_eq!(tiny.cell_size, MIN_CELL_SIZE);
+        for index in [grid(), tiny] {
+            let all = index.within_bbox(
+                &Coordinates::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+                &Coordinates::new(f64::INFINITY, f64::INFINITY),
+            );
+            assert_eq!(all.len(), 400);
+
+            let corner = index.within_bbox(
+                &Coordinates::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+                &Coordinates::new(1.2805, 103.8405),
+            );
+            assert_eq!(corner.len(), 1);
+        }
+    }
+}
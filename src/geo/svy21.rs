@@ -0,0 +1,259 @@
+//! Conversion between SVY21 (EPSG:3414) and WGS84 (EPSG:4326)
+//!
+//! SVY21 is a transverse Mercator projection on the WGS84 ellipsoid. The formulas and
+//! parameters follow the ones published by the Singapore Land Authority.
+
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::utils::Coordinates;
+
+/// Semi-major axis of the WGS84 ellipsoid, in metres
+const A: f64 = 6378137.0;
+
+/// Flattening of the WGS84 ellipsoid
+const F: f64 = 1.0 / 298.257223563;
+
+/// Latitude of the origin, 1°22'N
+const ORIGIN_LAT: f64 = 1.0 + 22.0 / 60.0;
+
+/// Longitude of the origin, 103°50'E
+const ORIGIN_LONG: f64 = 103.0 + 50.0 / 60.0;
+
+/// False northing, in metres
+const ORIGIN_N: f64 = 38744.572;
+
+/// False easting, in metres
+const ORIGIN_E: f64 = 28001.642;
+
+/// Scale factor on the central meridian
+const K: f64 = 1.0;
+
+const B: f64 = A * (1.0 - F);
+const E2: f64 = 2.0 * F - F * F;
+const E4: f64 = E2 * E2;
+const E6: f64 = E4 * E2;
+
+const A0: f64 = 1.0 - (E2 / 4.0) - (3.0 * E4 / 64.0) - (5.0 * E6 / 256.0);
+const A2: f64 = (3.0 / 8.0) * (E2 + (E4 / 4.0) + (15.0 * E6 / 128.0));
+const A4: f64 = (15.0 / 256.0) * (E4 + (3.0 * E6 / 4.0));
+const A6: f64 = 35.0 * E6 / 3072.0;
+
+/// Coordinates in SVY21, in metres
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Svy21 {
+    pub northing: f64,
+    pub easting: f64,
+}
+
+impl Svy21 {
+    pub fn new(northing: f64, easting: f64) -> Self {
+        Svy21 { northing, easting }
+    }
+
+    /// Projects WGS84 latitude and longitude (in degrees) to SVY21
+    pub fn from_wgs84(lat: f64, long: f64) -> Self {
+        let lat_r = lat.to_radians();
+        let sin_lat = lat_r.sin();
+        let sin2_lat = sin_lat * sin_lat;
+        let cos_lat = lat_r.cos();
+        let cos2_lat = cos_lat * cos_lat;
+        let cos3_lat = cos2_lat * cos_lat;
+        let cos4_lat = cos3_lat * cos_lat;
+        let cos5_lat = cos4_lat * cos_lat;
+        let cos6_lat = cos5_lat * cos_lat;
+        let cos7_lat = cos6_lat * cos_lat;
+
+        let rho = calc_rho(sin2_lat);
+        let v = calc_v(sin2_lat);
+        let psi = v / rho;
+        let t = lat_r.tan();
+        let w = (long - ORIGIN_LONG).to_radians();
+
+        let m = calc_m(lat);
+        let m_o = calc_m(ORIGIN_LAT);
+
+        let w2 = w * w;
+        let w4 = w2 * w2;
+        let w6 = w4 * w2;
+        let w8 = w6 * w2;
+
+        let psi2 = psi * psi;
+        let psi3 = psi2 * psi;
+        let psi4 = psi3 * psi;
+
+        let t2 = t * t;
+        let t4 = t2 * t2;
+        let t6 = t4 * t2;
+
+        let n_term1 = w2 / 2.0 * v * sin_lat * cos_lat;
+        let n_term2 = w4 / 24.0 * v * sin_lat * cos3_lat * (4.0 * psi2 + psi - t2);
+        let n_term3 = w6 / 720.0
+            * v
+            * sin_lat
+            * cos5_lat
+            * ((8.0 * psi4) * (11.0 - 24.0 * t2) - (28.0 * psi3) * (1.0 - 6.0 * t2)
+                + psi2 * (1.0 - 32.0 * t2)
+                - psi * (2.0 * t2)
+                + t4);
+        let n_term4 =
+            w8 / 40320.0 * v * sin_lat * cos7_lat * (1385.0 - 3111.0 * t2 + 543.0 * t4 - t6);
+        let northing = ORIGIN_N + K * (m - m_o + n_term1 + n_term2 + n_term3 + n_term4);
+
+        let e_term1 = w2 / 6.0 * cos2_lat * (psi - t2);
+        let e_term2 = w4 / 120.0
+            * cos4_lat
+            * ((4.0 * psi3) * (1.0 - 6.0 * t2) + psi2 * (1.0 + 8.0 * t2) - psi * 2.0 * t2 + t4);
+        let e_term3 = w6 / 5040.0 * cos6_lat * (61.0 - 479.0 * t2 + 179.0 * t4 - t6);
+        let easting = ORIGIN_E + K * v * w * cos_lat * (1.0 + e_term1 + e_term2 + e_term3);
+
+        Svy21 { northing, easting }
+    }
+
+    /// Returns the WGS84 latitude and longitude (in degrees) of `self`
+    pub fn to_wgs84(&self) -> Coordinates {
+        let n_prime = self.northing - ORIGIN_N;
+        let m_o = calc_m(ORIGIN_LAT);
+        let m_prime = m_o + (n_prime / K);
+
+        let n = (A - B) / (A + B);
+        let n2 = n * n;
+        let n3 = n2 * n;
+        let n4 = n2 * n2;
+
+        let g = A
+            * (1.0 - n)
+            * (1.0 - n2)
+            * (1.0 + (9.0 * n2 / 4.0) + (225.0 * n4 / 64.0))
+            * (PI / 180.0);
+        let sigma = (m_prime * PI) / (180.0 * g);
+
+        let lat_prime = sigma
+            + ((3.0 * n / 2.0) - (27.0 * n3 / 32.0)) * (2.0 * sigma).sin()
+            + ((21.0 * n2 / 16.0) - (55.0 * n4 / 32.0)) * (4.0 * sigma).sin()
+            + (151.0 * n3 / 96.0) * (6.0 * sigma).sin()
+            + (1097.0 * n4 / 512.0) * (8.0 * sigma).sin();
+
+        let sin_lat_prime = lat_prime.sin();
+        let sin2_lat_prime = sin_lat_prime * sin_lat_prime;
+
+        let rho_prime = calc_rho(sin2_lat_prime);
+        let v_prime = calc_v(sin2_lat_prime);
+        let psi_prime = v_prime / rho_prime;
+        let psi_prime2 = psi_prime * psi_prime;
+        let psi_prime3 = psi_prime2 * psi_prime;
+        let psi_prime4 = psi_prime3 * psi_prime;
+
+        let t_prime = lat_prime.tan();
+        let t_prime2 = t_prime * t_prime;
+        let t_prime4 = t_prime2 * t_prime2;
+        let t_prime6 = t_prime4 * t_prime2;
+
+        let e_prime = self.easting - ORIGIN_E;
+        let x = e_prime / (K * v_prime);
+        let x2 = x * x;
+        let x3 = x2 * x;
+        let x5 = x3 * x2;
+        let x7 = x5 * x2;
+
+        let lat_factor = t_prime / (K * rho_prime);
+        let lat_term1 = lat_factor * ((e_prime * x) / 2.0);
+        let lat_term2 = lat_factor
+            * ((e_prime * x3) / 24.0)
+            * ((-4.0 * psi_prime2) + (9.0 * psi_prime) * (1.0 - t_prime2) + (12.0 * t_prime2));
+        let lat_term3 = lat_factor
+            * ((e_prime * x5) / 720.0)
+            * ((8.0 * psi_prime4) * (11.0 - 24.0 * t_prime2)
+                - (12.0 * psi_prime3) * (21.0 - 71.0 * t_prime2)
+                + (15.0 * psi_prime2) * (15.0 - 98.0 * t_prime2 + 15.0 * t_prime4)
+                + (180.0 * psi_prime) * (5.0 * t_prime2 - 3.0 * t_prime4)
+                + 360.0 * t_prime4);
+        let lat_term4 = lat_factor
+            * ((e_prime * x7) / 40320.0)
+            * (1385.0 - 3633.0 * t_prime2 + 4095.0 * t_prime4 + 1575.0 * t_prime6);
+        let lat = lat_prime - lat_term1 + lat_term2 - lat_term3 + lat_term4;
+
+        let sec_lat_prime = 1.0 / lat.cos();
+        let long_term1 = x * sec_lat_prime;
+        let long_term2 = ((x3 * sec_lat_prime) / 6.0) * (psi_prime + 2.0 * t_prime2);
+        let long_term3 = ((x5 * sec_lat_prime) / 120.0)
+            * ((-4.0 * psi_prime3) * (1.0 - 6.0 * t_prime2)
+                + psi_prime2 * (9.0 - 68.0 * t_prime2)
+                + 72.0 * psi_prime * t_prime2
+                + 24.0 * t_prime4);
+        let long_term4 = ((x7 * sec_lat_prime) / 5040.0)
+            * (61.0 + 662.0 * t_prime2 + 1320.0 * t_prime4 + 720.0 * t_prime6);
+        let long = ORIGIN_LONG.to_radians() + long_term1 - long_term2 + long_term3 - long_term4;
+
+        Coordinates::new(lat.to_degrees(), long.to_degrees())
+    }
+}
+
+/// Meridional arc length from the equator to `lat` (in degrees)
+fn calc_m(lat: f64) -> f64 {
+    let lat_r = lat.to_radians();
+    A * ((A0 * lat_r) - (A2 * (2.0 * lat_r).sin()) + (A4 * (4.0 * lat_r).sin())
+        - (A6 * (6.0 * lat_r).sin()))
+}
+
+/// Radius of curvature in the meridian
+fn calc_rho(sin2_lat: f64) -> f64 {
+    let num = A * (1.0 - E2);
+    let denom = (1.0 - E2 * sin2_lat).powf(1.5);
+    num / denom
+}
+
+/// Radius of curvature in the prime vertical
+fn calc_v(sin2_lat: f64) -> f64 {
+    A / (1.0 - E2 * sin2_lat).sqrt()
+}
+
+impl From<Coordinates> for Svy21 {
+    fn from(coords: Coordinates) -> Self {
+        Svy21::from_wgs84(coords.lat, coords.long)
+    }
+}
+
+impl From<&Coordinates> for Svy21 {
+    fn from(coords: &Coordinates) -> Self {
+        Svy21::from_wgs84(coords.lat, coords.long)
+    }
+}
+
+impl From<Svy21> for Coordinates {
+    fn from(svy21: Svy21) -> Self {
+        svy21.to_wgs84()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn onemap_reference_point() {
+        // Example of the OneMap coordinate conversion API (`/api/common/convert/4326to3414`
+        // and `3414to4326`). OneMap rounds the origin to 1.366666 and 103.833333, which moves
+        // its results by 7cm northing and 4cm easting from the published SVY21 origin
+        let (lat, long) = (1.319728905, 103.8421581);
+        let (northing, easting) = (33554.5098132059, 28983.788791079794);
+
+        let svy21 = Svy21::from_wgs84(lat, long);
+        assert!((svy21.northing - northing).abs() < 0.1, "{:?}", svy21);
+        assert!((svy21.easting - easting).abs() < 0.1, "{:?}", svy21);
+
+        let coords = Svy21::new(northing, easting).to_wgs84();
+        assert!((coords.lat - lat).abs() < 1e-6, "{:?}", coords);
+        assert!((coords.long - long).abs() < 1e-6, "{:?}", coords);
+    }
+
+    #[test]
+    fn round_trip() {
+        for (lat, long) in [(1.2, 103.6), (1.47, 104.05), (1.35, 103.82)] {
+            let coords = Svy21::from_wgs84(lat, long).to_wgs84();
+            assert!((coords.lat - lat).abs() < 1e-8);
+            assert!((coords.long - long).abs() < 1e-8);
+        }
+    }
+}
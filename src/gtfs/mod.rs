@@ -0,0 +1,7 @@
+#[cfg(feature = "gtfs-rt")]
+pub mod realtime;
+pub mod schedule;
+
+#[cfg(feature = "gtfs-rt")]
+pub use realtime::{encode_feed, feed_message, FeedMessage};
+pub use schedule::{route_id, service_id, stop_id, trip_id, GtfsFeed, GtfsOptions};
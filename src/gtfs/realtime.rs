@@ -0,0 +1,525 @@
+//! GTFS-Realtime feed of the bus arrivals, with the IDs of [`GtfsFeed`](super::GtfsFeed)
+//!
+//! The messages are the subset of `gtfs-realtime.proto` that the arrival API can fill
+
+use std::collections::HashSet;
+
+use prost::{Enumeration, Message};
+use time::{Duration, OffsetDateTime};
+
+use super::schedule::{
+    gtfs_date, gtfs_time, route_id, seconds, stop_id, stop_sequence, trip_id, GtfsOptions,
+};
+use crate::bus::{NextBusExt, ServiceDirection, ServiceDirectory};
+use crate::models::bus::bus_arrival::ArrivalBusService;
+use crate::models::bus::prelude::{BusArrivalResp, NextBus};
+use crate::models::bus_enums::BusLoad;
+use crate::utils::SGT;
+
+pub const GTFS_REALTIME_VERSION: &str = "2.0";
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedMessage {
+    #[prost(message, required, tag = "1")]
+    pub header: FeedHeader,
+    #[prost(message, repeated, tag = "2")]
+    pub entity: Vec<FeedEntity>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedHeader {
+    #[prost(string, required, tag = "1")]
+    pub gtfs_realtime_version: String,
+    #[prost(enumeration = "Incrementality", optional, tag = "2")]
+    pub incrementality: Option<i32>,
+    #[prost(uint64, optional, tag = "3")]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum Incrementality {
+    FullDataset = 0,
+    Differential = 1,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedEntity {
+    #[prost(string, required, tag = "1")]
+    pub id: String,
+    #[prost(bool, optional, tag = "2")]
+    pub is_deleted: Option<bool>,
+    #[prost(message, optional, tag = "3")]
+    pub trip_update: Option<TripUpdate>,
+    #[prost(message, optional, tag = "4")]
+    pub vehicle: Option<VehiclePosition>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TripUpdate {
+    #[prost(message, required, tag = "1")]
+    pub trip: TripDescriptor,
+    #[prost(message, repeated, tag = "2")]
+    pub stop_time_update: Vec<StopTimeUpdate>,
+    #[prost(message, optional, tag = "3")]
+    pub vehicle: Option<VehicleDescriptor>,
+    #[prost(uint64, optional, tag = "4")]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StopTimeEvent {
+    #[prost(int32, optional, tag = "1")]
+    pub delay: Option<i32>,
+    #[prost(int64, optional, tag = "2")]
+    pub time: Option<i64>,
+    #[prost(int32, optional, tag = "3")]
+    pub uncertainty: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StopTimeUpdate {
+    #[prost(uint32, optional, tag = "1")]
+    pub stop_sequence: Option<u32>,
+    #[prost(message, optional, tag = "2")]
+    pub arrival: Option<StopTimeEvent>,
+    #[prost(message, optional, tag = "3")]
+    pub departure: Option<StopTimeEvent>,
+    #[prost(string, optional, tag = "4")]
+    pub stop_id: Option<String>,
+    #[prost(enumeration = "StopTimeScheduleRelationship", optional, tag = "5")]
+    pub schedule_relationship: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum StopTimeScheduleRelationship {
+    Scheduled = 0,
+    Skipped = 1,
+    NoData = 2,
+
+    /// Stop times of trips with `exact_times=0` in `frequencies.txt`
+    Unscheduled = 3,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TripDescriptor {
+    #[prost(string, optional, tag = "1")]
+    pub trip_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub start_time: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub start_date: Option<String>,
+    #[prost(enumeration = "TripScheduleRelationship", optional, tag = "4")]
+    pub schedule_relationship: Option<i32>,
+    #[prost(string, optional, tag = "5")]
+    pub route_id: Option<String>,
+    #[prost(uint32, optional, tag = "6")]
+    pub direction_id: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum TripScheduleRelationship {
+    Scheduled = 0,
+    Added = 1,
+
+    /// Trips of `frequencies.txt` with `exact_times=0`, which is how [`GtfsFeed`](super::GtfsFeed)
+    /// describes every trip
+    Unscheduled = 2,
+    Canceled = 3,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct VehicleDescriptor {
+    #[prost(string, optional, tag = "1")]
+    pub id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub label: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Position {
+    #[prost(float, required, tag = "1")]
+    pub latitude: f32,
+    #[prost(float, required, tag = "2")]
+    pub longitude: f32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct VehiclePosition {
+    #[prost(message, optional, tag = "1")]
+    pub trip: Option<TripDescriptor>,
+    #[prost(message, optional, tag = "2")]
+    pub position: Option<Position>,
+    #[prost(uint64, optional, tag = "5")]
+    pub timestamp: Option<u64>,
+    #[prost(message, optional, tag = "8")]
+    pub vehicle: Option<VehicleDescriptor>,
+    #[prost(enumeration = "OccupancyStatus", optional, tag = "9")]
+    pub occupancy_status: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum OccupancyStatus {
+    Empty = 0,
+    ManySeatsAvailable = 1,
+    FewSeatsAvailable = 2,
+    StandingRoomOnly = 3,
+    CrushedStandingRoomOnly = 4,
+    Full = 5,
+    NotAcceptingPassengers = 6,
+}
+
+impl OccupancyStatus {
+    pub fn from_load(load: &BusLoad) -> Option<Self> {
+        match load {
+            BusLoad::SeatsAvailable => Some(OccupancyStatus::ManySeatsAvailable),
+            BusLoad::StandingAvailable => Some(OccupancyStatus::StandingRoomOnly),
+            BusLoad::LimitedStanding => Some(OccupancyStatus::CrushedStandingRoomOnly),
+            BusLoad::Unknown => None,
+        }
+    }
+}
+
+/// Builds a full dataset `FeedMessage` from the arrivals at several stops.
+///
+/// The arrival API does not identify buses, so every upcoming bus at a stop is a `TripUpdate`
+/// with a single stop time, and the trip is described by its route, direction and the
+/// unscheduled trip of `directory`'s static feed for the day. Pass the `options` the static
+/// feed was built with, so that holidays get the same trips. Buses whose location is known
+/// are also reported once each as a `VehiclePosition`
+pub fn feed_message(
+    directory: &ServiceDirectory,
+    arrivals: &[BusArrivalResp],
+    options: &GtfsOptions,
+    now: OffsetDateTime,
+) -> FeedMessage {
+    let timestamp = now.unix_timestamp().max(0) as u64;
+
+    let mut entity = Vec::new();
+    let mut vehicles = HashSet::new();
+
+    for resp in arrivals {
+        for service in &resp.services {
+            for (position, bus) in service.next_bus.iter().enumerate() {
+                let Some(bus) = bus else { continue };
+                let direction = direction_of(directory, service, bus).and_then(|direction| {
+                    directory.service(&service.service_no)?.direction(direction)
+                });
+                let stop = direction.and_then(|direction| {
+                    let call = directory.services_at(resp.bus_stop_code).iter().find(|c| {
+                        c.service_no == service.service_no && c.direction == direction.direction
+                    })?;
+                    direction.stops.iter().find(|s| s.stop_seq == call.stop_seq)
+                });
+
+                // Frequency based trips are identified by their start, which is estimated
+                // from the distance travelled since the first stop
+                let travelled = match (direction.and_then(|d| d.origin()), stop) {
+                    (Some(origin), Some(stop)) => (stop.dist - origin.dist).max(0.0),
+                    _ => 0.0,
+                };
+                let start = (bus.est_arrival
+                    - Duration::seconds_f64(travelled / options.average_speed.max(1.0) * 3600.0))
+                .to_offset(SGT);
+                let day_type = options.holidays.day_type(start.date());
+
+                let trip = TripDescriptor {
+                    trip_id: direction.map(|d| trip_id(&service.service_no, d.direction, day_type)),
+                    start_time: Some(gtfs_time(seconds(start.time()))),
+                    start_date: Some(gtfs_date(start.date())),
+                    schedule_relationship: Some(TripScheduleRelationship::Unscheduled as i32),
+                    route_id: Some(route_id(&service.service_no)),
+                    direction_id: direction.map(|d| d.direction.saturating_sub(1).min(1) as u32),
+                };
+                let id = format!(
+                    "{}-{}-{}",
+                    stop_id(resp.bus_stop_code),
+                    service.service_no,
+                    position
+                );
+
+                if bus.is_monitored()
+                    && vehicles.insert((
+                        service.service_no.as_str(),
+                        bus.lat.to_bits(),
+                        bus.long.to_bits(),
+                    ))
+                {
+                    entity.push(FeedEntity {
+                        id: format!("{}-vehicle", id),
+                        is_deleted: None,
+                        trip_update: None,
+                        vehicle: Some(VehiclePosition {
+                            trip: Some(trip.clone()),
+                            position: Some(Position {
+                                latitude: bus.lat as f32,
+                                longitude: bus.long as f32,
+                            }),
+                            timestamp: Some(timestamp),
+                            vehicle: None,
+                            occupancy_status: OccupancyStatus::from_load(&bus.load)
+                                .map(|o| o as i32),
+                        }),
+                    });
+                }
+
+                entity.push(FeedEntity {
+                    id,
+                    is_deleted: None,
+                    trip_update: Some(TripUpdate {
+                        trip,
+                        stop_time_update: vec![StopTimeUpdate {
+                            stop_sequence: direction
+                                .zip(stop)
+                                .and_then(|(d, s)| stop_sequence(d, s.stop_seq)),
+                            arrival: Some(StopTimeEvent {
+                                delay: None,
+                                time: Some(bus.est_arrival.unix_timestamp()),
+                                uncertainty: None,
+                            }),
+                            departure: None,
+                            stop_id: Some(stop_id(resp.bus_stop_code)),
+                            schedule_relationship: Some(
+                                StopTimeScheduleRelationship::Unscheduled as i32,
+                            ),
+                        }],
+                        vehicle: None,
+                        timestamp: Some(timestamp),
+                    }),
+                    vehicle: None,
+                });
+            }
+        }
+    }
+
+    FeedMessage {
+        header: FeedHeader {
+            gtfs_realtime_version: GTFS_REALTIME_VERSION.to_string(),
+            incrementality: Some(Incrementality::FullDataset as i32),
+            timestamp: Some(timestamp),
+        },
+        entity,
+    }
+}
+
+/// Protobuf encoding of [`feed_message`]
+pub fn encode_feed(
+    directory: &ServiceDirectory,
+    arrivals: &[BusArrivalResp],
+    options: &GtfsOptions,
+    now: OffsetDateTime,
+) -> Vec<u8> {
+    feed_message(directory, arrivals, options, now).encode_to_vec()
+}
+
+/// Direction whose first or last stop matches the origin or destination of the bus
+fn direction_of(
+    directory: &ServiceDirectory,
+    service: &ArrivalBusService,
+    bus: &NextBus,
+) -> Option<u8> {
+    let info = directory.service(&service.service_no)?;
+    let ends_at = |d: &&ServiceDirection| {
+        d.origin().map(|s| s.bus_stop_code) == Some(bus.origin_code)
+            || d.destination().map(|s| s.bus_stop_code) == Some(bus.dest_code)
+    };
+
+    match info.directions.as_slice() {
+        [only] => Some(only.direction),
+        directions => directions.iter().find(ends_at).map(|d| d.direction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::schedule::DEFAULT_AVERAGE_SPEED;
+    use crate::models::bus::prelude::{BusRoute, BusStop};
+    use crate::utils::HolidayCalendar;
+    use time::macros::{date, datetime};
+
+    fn directory() -> ServiceDirectory {
+        // Sequences have gaps and 1011 is missing from `get_bus_stops`
+        let routes = [(1, [1012, 1011, 1013, 1019]), (2, [1019, 1013, 1011, 1012])]
+            .into_iter()
+            .flat_map(|(direction, stops)| {
+                stops
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(i, bus_stop_code)| BusRoute {
+                        service_no: "12".to_string(),
+                        operator: Default::default(),
+                        direction,
+                        stop_seq: i as u8 * 2 + 1,
+                        bus_stop_code,
+                        dist: i as f64,
+                        wd_first: None,
+                        wd_last: None,
+                        sat_first: None,
+                        sat_last: None,
+                        sun_first: None,
+                        sun_last: None,
+                    })
+            })
+            .collect::<Vec<_>>();
+        let stops = [1012, 1013, 1019]
+            .into_iter()
+            .map(|bus_stop_code| BusStop {
+                bus_stop_code,
+                road_name: String::new(),
+                desc: bus_stop_code.to_string(),
+                lat: 1.29,
+                long: 103.85,
+            })
+            .collect::<Vec<_>>();
+        ServiceDirectory::new(&[], &routes, &stops)
+    }
+
+    fn options(holidays: HolidayCalendar) -> GtfsOptions {
+        GtfsOptions {
+            start_date: date!(2024 - 03 - 01),
+            end_date: date!(2024 - 03 - 31),
+            holidays,
+            average_speed: DEFAULT_AVERAGE_SPEED,
+        }
+    }
+
+    fn bus(origin_code: u32, dest_code: u32, lat: f64, long: f64) -> NextBus {
+        NextBus {
+            origin_code,
+            dest_code,
+            est_arrival: datetime!(2024-03-01 08:35 +8),
+            lat,
+            long,
+            visit_no: 1,
+            load: BusLoad::StandingAvailable,
+            feature: Default::default(),
+            bus_type: Default::default(),
+        }
+    }
+
+    fn arrivals(bus_stop_code: u32, next_bus: [Option<NextBus>; 3]) -> BusArrivalResp {
+        BusArrivalResp {
+            bus_stop_code,
+            services: vec![ArrivalBusService {
+                service_no: "12".to_string(),
+                operator: Default::default(),
+                next_bus,
+            }],
+        }
+    }
+
+    #[test]
+    fn trip_updates_and_vehicles() {
+        let directory = directory();
+        let monitored = bus(1019, 1012, 1.3, 103.85);
+        let feed = feed_message(
+            &directory,
+            &[
+                arrivals(
+                    1013,
+                    [
+                        Some(monitored.clone()),
+                        Some(bus(1012, 1019, 0.0, 0.0)),
+                        None,
+                    ],
+                ),
+                arrivals(1012, [Some(monitored), None, None]),
+            ],
+            &options(HolidayCalendar::singapore()),
+            datetime!(2024-03-01 08:30 +8),
+        );
+
+        let ids = feed
+            .entity
+            .iter()
+            .map(|e| e.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                "01013-12-0-vehicle",
+                "01013-12-0",
+                "01013-12-1",
+                "01012-12-0"
+            ]
+        );
+
+        let update = feed.entity[1].trip_update.as_ref().unwrap();
+        assert_eq!(update.trip.trip_id.as_deref(), Some("12-2-WD"));
+        assert_eq!(update.trip.direction_id, Some(1));
+        assert_eq!(update.trip.start_date.as_deref(), Some("20240301"));
+        // 1 km from the first stop at the average speed
+        assert_eq!(update.trip.start_time.as_deref(), Some("08:32:00"));
+        assert_eq!(update.stop_time_update[0].stop_sequence, Some(2));
+        assert_eq!(
+            update.stop_time_update[0].schedule_relationship,
+            Some(StopTimeScheduleRelationship::Unscheduled as i32)
+        );
+        assert_eq!(
+            update.stop_time_update[0].arrival.as_ref().unwrap().time,
+            Some(datetime!(2024-03-01 08:35 +8).unix_timestamp())
+        );
+
+        let vehicle = feed.entity[0].vehicle.as_ref().unwrap();
+        assert_eq!(
+            vehicle.occupancy_status,
+            Some(OccupancyStatus::StandingRoomOnly as i32)
+        );
+        let other = feed.entity[2].trip_update.as_ref().unwrap();
+        assert_eq!(other.trip.direction_id, Some(0));
+
+        // Numbered like `stop_times.txt`, which leaves out 1011
+        let last = feed.entity[3].trip_update.as_ref().unwrap();
+        assert_eq!(last.stop_time_update[0].stop_sequence, Some(3));
+    }
+
+    #[test]
+    fn holidays_of_the_static_feed() {
+        let directory = directory();
+        let arrivals = [arrivals(
+            1013,
+            [Some(bus(1019, 1012, 0.0, 0.0)), None, None],
+        )];
+        let trip_id = |holidays| {
+            let feed = feed_message(
+                &directory,
+                &arrivals,
+                &options(holidays),
+                datetime!(2024-03-01 08:30 +8),
+            );
+            feed.entity[0]
+                .trip_update
+                .as_ref()
+                .unwrap()
+                .trip
+                .trip_id
+                .clone()
+        };
+
+        assert_eq!(trip_id(HolidayCalendar::new()).as_deref(), Some("12-2-WD"));
+        let holidays = [date!(2024 - 03 - 01)].into_iter().collect();
+        assert_eq!(trip_id(holidays).as_deref(), Some("12-2-SUN"));
+    }
+
+    #[test]
+    fn encodes_and_decodes() {
+        let directory = directory();
+        let bytes = encode_feed(
+            &directory,
+            &[arrivals(
+                1013,
+                [Some(bus(1012, 1019, 1.3, 103.85)), None, None],
+            )],
+            &options(HolidayCalendar::singapore()),
+            datetime!(2024-03-01 08:30 +8),
+        );
+
+        let feed = FeedMessage::decode(bytes.as_slice()).unwrap();
+        assert_eq!(feed.header.gtfs_realtime_version, "2.0");
+        assert_eq!(feed.entity.len(), 2);
+    }
+}
@@ -0,0 +1,621 @@
+//! GTFS static feed of the bus network, with frequency based trips
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{Cursor, Seek, Write};
+
+use time::{Date, Duration, Time, Weekday};
+use zip::write::SimpleFileOptions;
+
+use crate::bus::timings::operating_span;
+use crate::bus::{ServiceDirection, ServiceDirectory};
+use crate::models::bus::prelude::{BusFreq, BusRoute, BusService, BusStop};
+use crate::models::bus_enums::Operator;
+use crate::utils::{calendar, today_sgt, DayType, HolidayCalendar};
+use crate::LTAResult;
+
+/// Start of each frequency band of `get_bus_services`, in seconds after midnight
+const AM_PEAK: i64 = 6 * 3600 + 30 * 60;
+const AM_OFFPEAK: i64 = 8 * 3600 + 30 * 60;
+const PM_PEAK: i64 = 17 * 3600;
+const PM_OFFPEAK: i64 = 19 * 3600;
+
+const DAY: i64 = 24 * 3600;
+
+/// Default of [`GtfsOptions::average_speed`], in km/h
+pub const DEFAULT_AVERAGE_SPEED: f64 = 20.0;
+
+/// Options for [`GtfsFeed::new`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GtfsOptions {
+    /// First day of `calendar.txt`
+    pub start_date: Date,
+
+    /// Last day of `calendar.txt`
+    pub end_date: Date,
+
+    /// Holidays, which follow the Sunday timings in `calendar_dates.txt`
+    pub holidays: HolidayCalendar,
+
+    /// Speed used to time the stops of a route when the first bus timings do not, in km/h
+    pub average_speed: f64,
+}
+
+impl Default for GtfsOptions {
+    /// A year from today, with the holidays of [`calendar::holidays`]
+    fn default() -> Self {
+        let start_date = today_sgt();
+        GtfsOptions {
+            start_date,
+            end_date: start_date + Duration::days(364),
+            holidays: calendar::holidays(),
+            average_speed: DEFAULT_AVERAGE_SPEED,
+        }
+    }
+}
+
+/// `stop_id` of a bus stop, the zero padded bus stop code
+pub fn stop_id(bus_stop_code: u32) -> String {
+    format!("{:05}", bus_stop_code)
+}
+
+/// `route_id` of a service, the service number
+pub fn route_id(service_no: &str) -> String {
+    service_no.to_string()
+}
+
+/// `service_id` of a day type
+pub fn service_id(day_type: DayType) -> &'static str {
+    match day_type {
+        DayType::Weekday => "WD",
+        DayType::Saturday => "SAT",
+        DayType::SundayOrHoliday => "SUN",
+    }
+}
+
+/// `trip_id` of a direction of a service on a day type
+pub fn trip_id(service_no: &str, direction: u8, day_type: DayType) -> String {
+    format!("{}-{}-{}", service_no, direction, service_id(day_type))
+}
+
+/// `stop_sequence` of the stop at `stop_seq` of a direction, its position among the stops of
+/// the direction that are in `get_bus_stops` starting from 1. `None` for stops that are not in
+/// `stops.txt`
+pub(crate) fn stop_sequence(direction: &ServiceDirection, stop_seq: u8) -> Option<u32> {
+    direction
+        .stops
+        .iter()
+        .filter(|s| s.desc.is_some())
+        .position(|s| s.stop_seq == stop_seq)
+        .map(|i| i as u32 + 1)
+}
+
+/// Text files of a GTFS static feed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GtfsFeed {
+    files: BTreeMap<&'static str, String>,
+}
+
+impl GtfsFeed {
+    /// Builds the feed from the full results of `get_bus_services`, `get_bus_routes` and
+    /// `get_bus_stops`. Each direction of a service gets one trip per day type, repeated at
+    /// the peak and off-peak frequencies between its first and last bus
+    pub fn new(
+        services: &[BusService],
+        routes: &[BusRoute],
+        stops: &[BusStop],
+        options: &GtfsOptions,
+    ) -> Self {
+        let directory = ServiceDirectory::new(services, routes, stops);
+        let mut files = BTreeMap::new();
+
+        files.insert("agency.txt", agency(&directory));
+        files.insert("stops.txt", stops_txt(stops));
+        files.insert("calendar.txt", calendar_txt(options));
+        files.insert("calendar_dates.txt", calendar_dates_txt(options));
+
+        let mut routes_txt = String::from("route_id,agency_id,route_short_name,route_type\n");
+        let mut trips = String::from("route_id,service_id,trip_id,trip_headsign,direction_id\n");
+        let mut stop_times =
+            String::from("trip_id,arrival_time,departure_time,stop_id,stop_sequence\n");
+        let mut frequencies =
+            String::from("trip_id,start_time,end_time,headway_secs,exact_times\n");
+
+        let mut by_direction: BTreeMap<(&str, u8), Vec<&BusRoute>> = BTreeMap::new();
+        for r in routes {
+            by_direction
+                .entry((&r.service_no, r.direction))
+                .or_default()
+                .push(r);
+        }
+
+        for service in directory.services() {
+            let _ = writeln!(
+                routes_txt,
+                "{},{},{},3",
+                csv(&route_id(&service.service_no)),
+                agency_id(&service.operator),
+                csv(&service.service_no)
+            );
+
+            for direction in &service.directions {
+                let Some(rows) =
+                    by_direction.get(&(service.service_no.as_str(), direction.direction))
+                else {
+                    continue;
+                };
+                // Stops missing from `get_bus_stops` are left out, as they are not in
+                // `stops.txt`. A trip needs at least two stops
+                let mut rows = rows
+                    .iter()
+                    .filter(|r| directory.stop(r.bus_stop_code).is_some())
+                    .copied()
+                    .collect::<Vec<_>>();
+                if rows.len() < 2 {
+                    continue;
+                }
+                rows.sort_by_key(|r| r.stop_seq);
+
+                for day_type in [
+                    DayType::Weekday,
+                    DayType::Saturday,
+                    DayType::SundayOrHoliday,
+                ] {
+                    let Some((start, end)) = first_last(rows[0], day_type) else {
+                        continue;
+                    };
+                    let trip = trip_id(&service.service_no, direction.direction, day_type);
+                    let headsign = direction
+                        .destination()
+                        .and_then(|d| d.desc.clone())
+                        .unwrap_or_default();
+
+                    let _ = writeln!(
+                        trips,
+                        "{},{},{},{},{}",
+                        csv(&route_id(&service.service_no)),
+                        service_id(day_type),
+                        csv(&trip),
+                        csv(&headsign),
+                        direction.direction.saturating_sub(1).min(1)
+                    );
+
+                    for (r, offset) in
+                        rows.iter()
+                            .zip(offsets(&rows, day_type, options.average_speed))
+                    {
+                        let Some(seq) = stop_sequence(direction, r.stop_seq) else {
+                            continue;
+                        };
+                        let t = gtfs_time(start + offset);
+                        let _ = writeln!(
+                            stop_times,
+                            "{},{},{},{},{}",
+                            csv(&trip),
+                            t,
+                            t,
+                            stop_id(r.bus_stop_code),
+                            seq
+                        );
+                    }
+
+                    for (from, to, headway) in headways(direction, start, end) {
+                        let _ = writeln!(
+                            frequencies,
+                            "{},{},{},{},0",
+                            csv(&trip),
+                            gtfs_time(from),
+                            gtfs_time(to),
+                            headway
+                        );
+                    }
+                }
+            }
+        }
+
+        files.insert("routes.txt", routes_txt);
+        files.insert("trips.txt", trips);
+        files.insert("stop_times.txt", stop_times);
+        files.insert("frequencies.txt", frequencies);
+        GtfsFeed { files }
+    }
+
+    /// Contents of one of the files, e.g. `stops.txt`
+    pub fn file(&self, name: &str) -> Option<&str> {
+        self.files.get(name).map(String::as_str)
+    }
+
+    /// Names of the files in the feed
+    pub fn file_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.files.keys().copied()
+    }
+
+    /// Writes the feed as a zip archive
+    pub fn write_zip<W: Write + Seek>(&self, writer: W) -> LTAResult<()> {
+        let mut zip = zip::ZipWriter::new(writer);
+        let options = SimpleFileOptions::default();
+
+        for (name, contents) in &self.files {
+            zip.start_file(*name, options)
+                .map_err(std::io::Error::from)?;
+            zip.write_all(contents.as_bytes())?;
+        }
+        zip.finish().map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// Returns the feed as a zip archive
+    pub fn to_zip(&self) -> LTAResult<Vec<u8>> {
+        let mut buf = Cursor::new(Vec::new());
+        self.write_zip(&mut buf)?;
+        Ok(buf.into_inner())
+    }
+}
+
+fn agency_id(operator: &Operator) -> &'static str {
+    match operator {
+        Operator::Sbst => "SBST",
+        Operator::Smrt => "SMRT",
+        Operator::Tts => "TTS",
+        Operator::Gas => "GAS",
+        Operator::Unknown => "LTA",
+    }
+}
+
+fn agency(directory: &ServiceDirectory) -> String {
+    let mut operators = directory
+        .services()
+        .map(|s| agency_id(&s.operator))
+        .collect::<Vec<_>>();
+    operators.sort_unstable();
+    operators.dedup();
+
+    let mut out = String::from("agency_id,agency_name,agency_url,agency_timezone\n");
+    for id in operators {
+        let (name, url) = match id {
+            "SBST" => ("SBS Transit", "https://www.sbstransit.com.sg"),
+            "SMRT" => ("SMRT Buses", "https://www.smrt.com.sg"),
+            "TTS" => ("Tower Transit Singapore", "https://towertransit.sg"),
+            "GAS" => ("Go-Ahead Singapore", "https://go-aheadsingapore.com"),
+            _ => ("Land Transport Authority", "https://www.lta.gov.sg"),
+        };
+        let _ = writeln!(out, "{},{},{},Asia/Singapore", id, name, url);
+    }
+    out
+}
+
+fn stops_txt(stops: &[BusStop]) -> String {
+    let mut stops = stops.iter().collect::<Vec<_>>();
+    stops.sort_by_key(|s| s.bus_stop_code);
+
+    let mut out = String::from("stop_id,stop_code,stop_name,stop_desc,stop_lat,stop_lon\n");
+    for s in stops {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            stop_id(s.bus_stop_code),
+            stop_id(s.bus_stop_code),
+            csv(&s.desc),
+            csv(&s.road_name),
+            s.lat,
+            s.long
+        );
+    }
+    out
+}
+
+pub(crate) fn gtfs_date(date: Date) -> String {
+    format!(
+        "{:04}{:02}{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+fn calendar_txt(options: &GtfsOptions) -> String {
+    let (start, end) = (gtfs_date(options.start_date), gtfs_date(options.end_date));
+    let mut out = String::from(
+        "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n",
+    );
+    for (id, days) in [
+        ("WD", "1,1,1,1,1,0,0"),
+        ("SAT", "0,0,0,0,0,1,0"),
+        ("SUN", "0,0,0,0,0,0,1"),
+    ] {
+        let _ = writeln!(out, "{},{},{},{}", id, days, start, end);
+    }
+    out
+}
+
+/// Holidays that are not on a Sunday run the Sunday service instead of the usual one
+fn calendar_dates_txt(options: &GtfsOptions) -> String {
+    let mut out = String::from("service_id,date,exception_type\n");
+    let mut date = options.start_date;
+
+    while date <= options.end_date {
+        if options.holidays.is_holiday(date) && date.weekday() != Weekday::Sunday {
+            let usual = service_id(DayType::for_weekday(date.weekday()));
+            let _ = writeln!(out, "{},{},2", usual, gtfs_date(date));
+            let _ = writeln!(out, "SUN,{},1", gtfs_date(date));
+        }
+        match date.next_day() {
+            Some(next) => date = next,
+            None => break,
+        }
+    }
+    out
+}
+
+fn first_last_of(r: &BusRoute, day_type: DayType) -> Option<(Time, Time)> {
+    match day_type {
+        DayType::Weekday => r.wd_first.zip(r.wd_last),
+        DayType::Saturday => r.sat_first.zip(r.sat_last),
+        DayType::SundayOrHoliday => r.sun_first.zip(r.sun_last),
+    }
+}
+
+pub(crate) fn seconds(t: Time) -> i64 {
+    t.hour() as i64 * 3600 + t.minute() as i64 * 60 + t.second() as i64
+}
+
+/// First and last bus at a stop in seconds after midnight, where the last bus goes past 24
+/// hours when it runs past midnight
+fn first_last(r: &BusRoute, day_type: DayType) -> Option<(i64, i64)> {
+    let (first, last) = first_last_of(r, day_type)?;
+    let start = seconds(first);
+    Some((start, start + operating_span(first, last).whole_seconds()))
+}
+
+/// Time from the first stop to each stop, in seconds. Taken from the first bus timings
+/// when they are known for every stop and never go backwards, otherwise from the distance
+fn offsets(rows: &[&BusRoute], day_type: DayType, average_speed: f64) -> Vec<i64> {
+    let first_bus = rows
+        .iter()
+        .map(|r| first_last_of(r, day_type).map(|(first, _)| seconds(first)))
+        .collect::<Option<Vec<_>>>();
+
+    if let Some(first_bus) = first_bus {
+        let offsets = first_bus
+            .iter()
+            .map(|&t| (t - first_bus[0]).rem_euclid(DAY))
+            .collect::<Vec<_>>();
+        if offsets.windows(2).all(|w| w[0] <= w[1]) {
+            return offsets;
+        }
+    }
+
+    let speed = average_speed.max(1.0);
+    rows.iter()
+        .map(|r| (((r.dist - rows[0].dist).max(0.0) / speed) * 3600.0).round() as i64)
+        .collect()
+}
+
+/// Minutes between buses, the middle of the range when there is one
+fn headway(freq: &BusFreq) -> Option<i64> {
+    let min = freq.min?.get() as i64;
+    let max = freq.max.map_or(min, |m| m.get() as i64);
+    Some((min + max) * 30)
+}
+
+/// Frequency bands clipped to the operating hours, as (start, end, headway in seconds)
+fn headways(direction: &ServiceDirection, start: i64, end: i64) -> Vec<(i64, i64, i64)> {
+    let Some(f) = &direction.frequencies else {
+        return Vec::new();
+    };
+
+    // Before the AM peak buses run at the AM off-peak frequency
+    let bands = [
+        (0, AM_PEAK, &f.am_offpeak),
+        (AM_PEAK, AM_OFFPEAK, &f.am_peak),
+        (AM_OFFPEAK, PM_PEAK, &f.am_offpeak),
+        (PM_PEAK, PM_OFFPEAK, &f.pm_peak),
+        (PM_OFFPEAK, 2 * DAY, &f.pm_offpeak),
+    ];
+
+    bands
+        .into_iter()
+        .filter_map(|(from, to, freq)| {
+            let (from, to) = (from.max(start), to.min(end));
+            (from < to).then_some((from, to, headway(freq)?))
+        })
+        .collect()
+}
+
+/// `HH:MM:SS`, where hours go past 24 for trips that run past midnight
+pub(crate) fn gtfs_time(secs: i64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Quotes a CSV field when needed
+fn csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::bus_enums::BusCategory;
+    use std::io::Read;
+    use time::macros::{date, time};
+
+    fn feed() -> GtfsFeed {
+        let routes = [(1012, 0.0, time!(05:30)), (1013, 0.6, time!(05:32))]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (bus_stop_code, dist, first))| BusRoute {
+                service_no: "12".to_string(),
+                operator: Operator::Sbst,
+                direction: 1,
+                stop_seq: i as u8 + 1,
+                bus_stop_code,
+                dist,
+                wd_first: Some(first),
+                wd_last: Some(time!(00:10)),
+                // A single trip on Saturdays
+                sat_first: Some(first),
+                sat_last: Some(first),
+                sun_first: Some(first),
+                sun_last: Some(time!(23:00)),
+            })
+            .collect::<Vec<_>>();
+        let stops = [
+            (1012, "Hotel Grand Pacific"),
+            (1013, "St. Joseph's Ch, Bras Basah"),
+        ]
+        .into_iter()
+        .map(|(bus_stop_code, desc)| BusStop {
+            bus_stop_code,
+            road_name: "Victoria St".to_string(),
+            desc: desc.to_string(),
+            lat: 1.29,
+            long: 103.85,
+        })
+        .collect::<Vec<_>>();
+        let services = [BusService {
+            service_no: "12".to_string(),
+            operator: Operator::Sbst,
+            no_direction: 1,
+            category: BusCategory::Trunk,
+            origin_code: None,
+            dest_code: None,
+            am_peak_freq: BusFreq::new(5, 9),
+            am_offpeak_freq: BusFreq::new(10, 12),
+            pm_peak_freq: BusFreq::no_max(8),
+            pm_offpeak_freq: BusFreq::no_timing(),
+            loop_desc: String::new(),
+        }];
+
+        let options = GtfsOptions {
+            start_date: date!(2024 - 03 - 25),
+            end_date: date!(2024 - 04 - 07),
+            holidays: HolidayCalendar::singapore(),
+            average_speed: 20.0,
+        };
+        GtfsFeed::new(&services, &routes, &stops, &options)
+    }
+
+    #[test]
+    fn frequency_based_trips() {
+        let feed = feed();
+
+        assert_eq!(
+            feed.file("stop_times.txt").unwrap(),
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+             12-1-WD,05:30:00,05:30:00,01012,1\n\
+             12-1-WD,05:32:00,05:32:00,01013,2\n\
+             12-1-SAT,05:30:00,05:30:00,01012,1\n\
+             12-1-SAT,05:32:00,05:32:00,01013,2\n\
+             12-1-SUN,05:30:00,05:30:00,01012,1\n\
+             12-1-SUN,05:32:00,05:32:00,01013,2\n"
+        );
+        // The single Saturday trip has no headways
+        assert!(!feed.file("frequencies.txt").unwrap().contains("-SAT,"));
+        // Weekday service runs past midnight, PM off-peak has no frequency
+        assert!(feed.file("frequencies.txt").unwrap().starts_with(
+            "trip_id,start_time,end_time,headway_secs,exact_times\n\
+                          12-1-WD,05:30:00,06:30:00,660,0\n\
+                          12-1-WD,06:30:00,08:30:00,420,0\n"
+        ));
+        assert!(feed
+            .file("stops.txt")
+            .unwrap()
+            .contains("01013,01013,\"St. Joseph's Ch, Bras Basah\",Victoria St"));
+        // Good Friday
+        assert!(feed
+            .file("calendar_dates.txt")
+            .unwrap()
+            .ends_with("WD,20240329,2\nSUN,20240329,1\n"));
+    }
+
+    #[test]
+    fn skips_unknown_stops() {
+        let route = |direction, stop_seq, bus_stop_code, dist| BusRoute {
+            service_no: "12".to_string(),
+            operator: Operator::Sbst,
+            direction,
+            stop_seq,
+            bus_stop_code,
+            dist,
+            wd_first: Some(time!(05:30)),
+            wd_last: Some(time!(23:00)),
+            sat_first: None,
+            sat_last: None,
+            sun_first: None,
+            sun_last: None,
+        };
+        // 1019 is not in `get_bus_stops`, nor is the only stop of direction 2
+        let routes = [
+            route(1, 1, 1012, 0.0),
+            route(1, 3, 1019, 0.3),
+            route(1, 4, 1013, 0.6),
+            route(2, 1, 9999, 0.0),
+        ];
+        let stops = [1012, 1013]
+            .into_iter()
+            .map(|bus_stop_code| BusStop {
+                bus_stop_code,
+                road_name: "Victoria St".to_string(),
+                desc: "Stop".to_string(),
+                lat: 1.29,
+                long: 103.85,
+            })
+            .collect::<Vec<_>>();
+        let options = GtfsOptions {
+            start_date: date!(2024 - 03 - 25),
+            end_date: date!(2024 - 04 - 07),
+            holidays: HolidayCalendar::singapore(),
+            average_speed: 36.0,
+        };
+
+        let feed = GtfsFeed::new(&[], &routes, &stops, &options);
+        assert_eq!(
+            feed.file("stop_times.txt").unwrap(),
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+             12-1-WD,05:30:00,05:30:00,01012,1\n\
+             12-1-WD,05:30:00,05:30:00,01013,2\n"
+        );
+        assert_eq!(feed.file("trips.txt").unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn zip_archive() {
+        let bytes = feed().to_zip().unwrap();
+        let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+
+        let mut names = zip.file_names().map(str::to_string).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "agency.txt",
+                "calendar.txt",
+                "calendar_dates.txt",
+                "frequencies.txt",
+                "routes.txt",
+                "stop_times.txt",
+                "stops.txt",
+                "trips.txt"
+            ]
+        );
+
+        let mut routes = String::new();
+        zip.by_name("routes.txt")
+            .unwrap()
+            .read_to_string(&mut routes)
+            .unwrap();
+        assert_eq!(
+            routes,
+            "route_id,agency_id,route_short_name,route_type\n12,SBST,12,3\n"
+        );
+    }
+}
@@ -42,6 +42,34 @@ pub mod ureq_blocking;
 #[cfg(feature = "reqwest-async")]
 pub mod reqwest_async;
 
+/// Helpers for the bus APIs
+pub mod bus;
+
+/// Helpers for the crowd APIs
+pub mod crowd;
+
+/// Helpers for the facilities APIs
+pub mod facility;
+
+/// Helpers for the geospatial APIs
+pub mod geo;
+
+/// Helpers for the traffic APIs
+pub mod traffic;
+
+/// Helpers for the train APIs
+pub mod train;
+
+/// GTFS feeds built from the bus APIs
+#[cfg(feature = "gtfs")]
+pub mod gtfs;
+
+/// Pre-signed links returned by some of the APIs
+pub mod link;
+
+/// Utilities shared across the different APIs
+pub mod utils;
+
 /// Type alias for `Result<T, LTAError>`
 pub type LTAResult<T> = Result<T, LTAError>;
 
@@ -83,6 +111,18 @@ pub enum LTAError {
     #[error("Undocumented status code, open an issue if this happens")]
     UnhandledStatusCode(StatusCode, String),
 
+    /// Passenger volume data is not available for the requested month
+    #[error("Passenger volume data is not available for `{0}`")]
+    UnavailableMonth(crowd::YearMonth),
+
+    /// The API no longer returns the link that was requested to be refreshed
+    #[error("Pre-signed link is no longer returned by the API")]
+    LinkUnavailable,
+
+    /// Failed to read or write a file
+    #[error("I/O error: `{0}`")]
+    Io(#[from] std::io::Error),
+
     /// Custom
     #[error("Custom error: `{0}`")]
     Custom(String),
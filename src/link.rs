@@ -0,0 +1,172 @@
+//! Pre-signed links returned by the passenger volume, geospatial, facilities maintenance and
+//! traffic flow APIs
+
+use std::fmt;
+
+use time::macros::format_description;
+use time::{Duration, OffsetDateTime, PrimitiveDateTime};
+
+use crate::crowd::YearMonth;
+use crate::models::crowd::passenger_vol::VolType;
+use crate::models::geo::prelude::GeospatialLayerId;
+use crate::models::train::StationCode;
+
+/// How long links last when the URL does not say, as documented by LTA
+pub const DEFAULT_LINK_LIFETIME: Duration = Duration::minutes(5);
+
+/// The request that returned a link, used to request a fresh one when it expires
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkSource {
+    PassengerVol {
+        vol_type: VolType,
+        month: Option<YearMonth>,
+        skip: Option<u32>,
+    },
+    GeospatialWholeIsland(GeospatialLayerId),
+    FacilitiesMaintenance(StationCode),
+    TrafficFlow,
+}
+
+/// A link to a file that expires shortly after it is obtained
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresignedLink {
+    url: String,
+    obtained_at: OffsetDateTime,
+    expires_at: OffsetDateTime,
+    source: LinkSource,
+    index: usize,
+}
+
+impl PresignedLink {
+    /// `index` is the position of the link in the response of `source`. The expiry is read
+    /// from the `X-Amz-Date` and `X-Amz-Expires` query parameters when present, otherwise
+    /// the link is assumed to last for [`DEFAULT_LINK_LIFETIME`]
+    pub fn new(
+        url: impl Into<String>,
+        obtained_at: OffsetDateTime,
+        source: LinkSource,
+        index: usize,
+    ) -> Self {
+        let url = url.into();
+        let expires_at = amz_expiry(&url).unwrap_or_else(|| obtained_at + DEFAULT_LINK_LIFETIME);
+
+        PresignedLink {
+            url,
+            obtained_at,
+            expires_at,
+            source,
+            index,
+        }
+    }
+
+    /// Wraps the links of a response that was just received
+    #[cfg(any(feature = "blocking-traits", feature = "non-blocking-traits"))]
+    pub(crate) fn from_urls(urls: Vec<String>, source: LinkSource) -> Vec<PresignedLink> {
+        let now = OffsetDateTime::now_utc();
+        urls.into_iter()
+            .enumerate()
+            .map(|(i, url)| PresignedLink::new(url, now, source.clone(), i))
+            .collect()
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn obtained_at(&self) -> OffsetDateTime {
+        self.obtained_at
+    }
+
+    pub fn expires_at(&self) -> OffsetDateTime {
+        self.expires_at
+    }
+
+    pub fn source(&self) -> &LinkSource {
+        &self.source
+    }
+
+    /// Position of the link in the response it came from
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns `true` if the link has expired as of `now`
+    pub fn is_expired_at(&self, now: OffsetDateTime) -> bool {
+        now >= self.expires_at
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(OffsetDateTime::now_utc())
+    }
+}
+
+impl fmt::Display for PresignedLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.url)
+    }
+}
+
+impl From<PresignedLink> for String {
+    fn from(link: PresignedLink) -> Self {
+        link.url
+    }
+}
+
+/// Reads the expiry of an S3 pre-signed URL
+fn amz_expiry(url: &str) -> Option<OffsetDateTime> {
+    let (_, query) = url.split_once('?')?;
+    let mut signed_at = None;
+    let mut lifetime = None;
+
+    for (k, v) in query.split('&').filter_map(|kv| kv.split_once('=')) {
+        match k {
+            "X-Amz-Date" => {
+                let fmt = format_description!("[year][month][day]T[hour][minute][second]Z");
+                signed_at = PrimitiveDateTime::parse(v, fmt)
+                    .ok()
+                    .map(|d| d.assume_utc());
+            }
+            "X-Amz-Expires" => lifetime = v.parse::<i64>().ok().map(Duration::seconds),
+            _ => {}
+        }
+    }
+
+    Some(signed_at? + lifetime?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn expiry_from_url() {
+        let url = "https://bucket.s3.amazonaws.com/file.zip?X-Amz-Algorithm=AWS4-HMAC-SHA256\
+                   &X-Amz-Date=20240301T083000Z&X-Amz-Expires=300&X-Amz-Signature=abc";
+        let link = PresignedLink::new(
+            url,
+            datetime!(2024-03-01 08:30:05 UTC),
+            LinkSource::TrafficFlow,
+            0,
+        );
+
+        assert_eq!(link.expires_at(), datetime!(2024-03-01 08:35:00 UTC));
+        assert!(!link.is_expired_at(datetime!(2024-03-01 08:34:59 UTC)));
+        assert!(link.is_expired_at(datetime!(2024-03-01 08:35:00 UTC)));
+    }
+
+    #[test]
+    fn default_expiry() {
+        let obtained_at = datetime!(2024-03-01 08:30:00 UTC);
+        let link = PresignedLink::new(
+            "https://example.com/file.json",
+            obtained_at,
+            LinkSource::FacilitiesMaintenance(StationCode::NS1),
+            2,
+        );
+
+        assert_eq!(link.expires_at(), obtained_at + DEFAULT_LINK_LIFETIME);
+        assert_eq!(link.index(), 2);
+        assert_eq!(link.to_string(), "https://example.com/file.json");
+    }
+}
@@ -6,12 +6,13 @@ use lta_models::{
     },
 };
 
+use crate::crowd::passenger_vol::{query_month, YearMonth};
+use crate::link::{LinkSource, PresignedLink};
 use crate::{
     r#async::ClientExt, reqwest_async::ReqwestAsync, vol_type_to_url, Client, Crowd, CrowdRequests,
     LTAClient, LTAResult,
 };
 use concat_string::concat_string;
-use time::{macros::format_description, Date};
 
 impl CrowdRequests<LTAClient<ReqwestAsync>> for Crowd {
     async fn get_passenger_vol_by<S, D>(
@@ -19,34 +20,36 @@ impl CrowdRequests<LTAClient<ReqwestAsync>> for Crowd {
         vol_type: VolType,
         date: D,
         skip: S,
-    ) -> LTAResult<Vec<String>>
+    ) -> LTAResult<Vec<PresignedLink>>
     where
         S: Into<Option<u32>>,
-        D: Into<Option<Date>>,
+        D: Into<Option<YearMonth>>,
     {
-        let format = format_description!("[year]-[month]-[day]");
-        let fmt_date = date
-            .into()
-            .map(|f| f.format(&format))
-            .expect("Failed to format.")
-            .ok();
+        let (month, skip) = (date.into(), skip.into());
+        let fmt_date = query_month(month)?;
+        let url = vol_type_to_url(client.base_url(), vol_type.clone())?;
 
-        let url = vol_type_to_url(client.base_url(), vol_type)?;
-
-        match fmt_date {
+        let urls: Vec<String> = match fmt_date {
             Some(nd) => {
                 client
                     .build_req_with_query::<passenger_vol::PassengerVolRawResp, _, _>(&url, |rb| {
                         rb.query(&[("Date", nd)])
                     })
-                    .await
+                    .await?
             }
             None => {
                 client
-                    .build_req_with_skip::<passenger_vol::PassengerVolRawResp, _>(&url, skip.into())
-                    .await
+                    .build_req_with_skip::<passenger_vol::PassengerVolRawResp, _>(&url, skip)
+                    .await?
             }
-        }
+        };
+
+        let source = LinkSource::PassengerVol {
+            vol_type,
+            month,
+            skip,
+        };
+        Ok(PresignedLink::from_urls(urls, source))
     }
 
     async fn get_crowd_density_rt(
@@ -1,3 +1,4 @@
+use crate::link::{LinkSource, PresignedLink};
 use crate::r#async::ClientExt;
 use crate::reqwest_async::ReqwestAsync;
 use crate::Client;
@@ -10,12 +11,14 @@ impl FacilityRequests<LTAClient<ReqwestAsync>> for Facility {
     async fn get_facilities_maintenance(
         client: &LTAClient<ReqwestAsync>,
         station_code: StationCode,
-    ) -> LTAResult<Vec<String>> {
+    ) -> LTAResult<Vec<PresignedLink>> {
+        let source = LinkSource::FacilitiesMaintenance(station_code.clone());
         client
             .build_req_with_query::<FacilityMaintenanceRawResp, _, _>(
                 &concat_string!(client.base_url(), "/FacilitiesMaintenance"),
                 |rb| rb.query(&[("StationCode", station_code)]),
             )
             .await
+            .map(|urls| PresignedLink::from_urls(urls, source))
     }
 }
@@ -46,6 +46,22 @@ impl ClientExt for LTAClient<ReqwestAsync> {
             .map(Into::into)
             .map_err(|_| LTAError::FailedToParseBody)
     }
+
+    async fn download(&self, url: &str) -> LTAResult<Vec<u8>> {
+        let res = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| LTAError::BackendError(Box::new(e)))?;
+
+        let res = handle_status_code(res).await?;
+
+        res.bytes()
+            .await
+            .map(|b| b.to_vec())
+            .map_err(|_| LTAError::FailedToParseBody)
+    }
 }
 
 async fn handle_status_code(res: reqwest::Response) -> LTAResult<reqwest::Response> {
@@ -93,10 +109,7 @@ mod tests {
 
     fn get_client() -> LTAClient<ReqwestAsync> {
         let api_key = env::var("API_KEY").expect("API_KEY does not exist!");
-        let client =
-            LTAClient::with_api_key(api_key, "http://datamall2.mytransport.sg/ltaodataservice")
-                .unwrap();
-        client
+        LTAClient::with_api_key(api_key, "http://datamall2.mytransport.sg/ltaodataservice").unwrap()
     }
 
     #[tokio::test]
@@ -107,6 +120,28 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn get_arrival_board() -> LTAResult<()> {
+        let client = get_client();
+        let board = Bus::get_arrival_board(&client, &[83139, 83151, 1012], None, 2).await;
+        println!("{:?}", board.entries);
+        assert!(board.failed.is_empty(), "{:?}", board.failed);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "Waits for the arrivals at the stop to change"]
+    async fn watch_arrival() -> LTAResult<()> {
+        use crate::bus::WatchOptions;
+        use futures_util::StreamExt;
+
+        let client = get_client();
+        let events = Bus::watch_arrival(&client, 83139, None, WatchOptions::default());
+        let event = Box::pin(events).next().await.unwrap()?;
+        println!("{:?}", event);
+        Ok(())
+    }
+
     #[tokio::test]
     #[ignore = "Ignored until LTA fixes their side. See issue#44"]
     async fn get_bus_services() -> LTAResult<()> {
@@ -152,6 +187,14 @@ mod tests {
         gen_test!(Traffic::get_carpark_avail)
     }
 
+    #[tokio::test]
+    async fn get_all_carpark_avail() -> LTAResult<()> {
+        let client = get_client();
+        let data = Traffic::get_all_carpark_avail(&client).await?;
+        println!("{}", data.len());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn get_est_travel_time() -> LTAResult<()> {
         gen_test!(Traffic::get_est_travel_time)
@@ -207,6 +250,39 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn get_traffic_flow_dataset() -> LTAResult<()> {
+        let client = get_client();
+        let data = Traffic::get_traffic_flow_dataset(&client).await?;
+        println!("{:?}", data.summary_by_category());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_traffic_images() -> LTAResult<()> {
+        let client = get_client();
+        let data = Traffic::download_traffic_images(&client, &[1001, 4703], 2).await?;
+        for (camera_id, image) in data {
+            println!(
+                "{} {:?}",
+                camera_id,
+                image.map(|i| (i.captured_at, i.bytes.len()))
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refresh_presigned_link() -> LTAResult<()> {
+        let client = get_client();
+        let link = Traffic::get_traffic_flow(&client).await?.remove(0);
+        let mut refreshed = link.refresh(&client).await?;
+        assert!(refreshed.obtained_at() >= link.obtained_at());
+        let data = refreshed.download(&client).await?;
+        println!("{}", data.len());
+        Ok(())
+    }
+
     #[ignore]
     #[tokio::test]
     async fn get_train_service_alerts() -> LTAResult<()> {
@@ -230,6 +306,15 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "shapefile")]
+    #[tokio::test]
+    async fn get_geospatial_layers() -> LTAResult<()> {
+        let client = get_client();
+        let data = Geo::get_geospatial_layers(&client, GeospatialLayerId::ArrowMarking).await?;
+        println!("{:?}", data);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn get_facility_maintenance() -> LTAResult<()> {
         let client = get_client();
@@ -238,6 +323,14 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn get_facility_maintenance_by_line() -> LTAResult<()> {
+        let client = get_client();
+        let data = Facility::get_facilities_maintenance_by_line(&client, MrtLine::BPL).await?;
+        println!("{:?}", data);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn get_crowd_density_rt() -> LTAResult<()> {
         let client = get_client();
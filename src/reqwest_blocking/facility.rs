@@ -1,5 +1,6 @@
 use lta_models::{facility::prelude::FacilityMaintenanceRawResp, prelude::StationCode};
 
+use crate::link::{LinkSource, PresignedLink};
 use crate::{
     blocking::{prelude::FacilityRequests, ClientExt, LTAClient},
     reqwest_blocking::ReqwestBlocking,
@@ -11,10 +12,13 @@ impl FacilityRequests<LTAClient<ReqwestBlocking>> for Facility {
     fn get_facilities_maintenance(
         client: &LTAClient<ReqwestBlocking>,
         station_code: StationCode,
-    ) -> LTAResult<Vec<String>> {
-        client.build_req_with_query::<FacilityMaintenanceRawResp, _, _>(
-            &concat_string!(client.base_url(), "/FacilitiesMaintenance"),
-            |rb| rb.query(&[("StationCode", station_code)]),
-        )
+    ) -> LTAResult<Vec<PresignedLink>> {
+        let source = LinkSource::FacilitiesMaintenance(station_code.clone());
+        client
+            .build_req_with_query::<FacilityMaintenanceRawResp, _, _>(
+                &concat_string!(client.base_url(), "/FacilitiesMaintenance"),
+                |rb| rb.query(&[("StationCode", station_code)]),
+            )
+            .map(|urls| PresignedLink::from_urls(urls, source))
     }
 }
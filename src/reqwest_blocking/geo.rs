@@ -1,5 +1,6 @@
 use lta_models::geo::prelude::{GeospatialLayerId, GeospatialWholeIslandRawResp};
 
+use crate::link::{LinkSource, PresignedLink};
 use crate::{
     blocking::{prelude::GeoRequests, ClientExt, LTAClient},
     reqwest_blocking::ReqwestBlocking,
@@ -11,10 +12,13 @@ impl GeoRequests<LTAClient<ReqwestBlocking>> for Geo {
     fn get_geospatial_whole_island(
         client: &LTAClient<ReqwestBlocking>,
         id: GeospatialLayerId,
-    ) -> LTAResult<Vec<String>> {
-        client.build_req_with_query::<GeospatialWholeIslandRawResp, _, _>(
-            &concat_string!(client.base_url(), "/GeospatialWholeIsland"),
-            |rb| rb.query(&[("ID", id)]),
-        )
+    ) -> LTAResult<Vec<PresignedLink>> {
+        let source = LinkSource::GeospatialWholeIsland(id.clone());
+        client
+            .build_req_with_query::<GeospatialWholeIslandRawResp, _, _>(
+                &concat_string!(client.base_url(), "/GeospatialWholeIsland"),
+                |rb| rb.query(&[("ID", id)]),
+            )
+            .map(|urls| PresignedLink::from_urls(urls, source))
     }
 }
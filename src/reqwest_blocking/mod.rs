@@ -43,6 +43,17 @@ impl ClientExt for LTAClient<ReqwestBlocking> {
             .map(Into::into)
             .map_err(|_| LTAError::FailedToParseBody)
     }
+
+    fn download(&self, url: &str) -> LTAResult<Vec<u8>> {
+        self.client
+            .get(url)
+            .send()
+            .map_err(|e| LTAError::BackendError(Box::new(e)))
+            .and_then(handle_status_code)?
+            .bytes()
+            .map(|b| b.to_vec())
+            .map_err(|_| LTAError::FailedToParseBody)
+    }
 }
 
 fn handle_status_code(res: Response) -> LTAResult<Response> {
@@ -93,10 +104,7 @@ mod tests {
 
     fn get_client() -> LTAClient<ReqwestBlocking> {
         let api_key = env::var("API_KEY").expect("API_KEY does not exist!");
-        let client =
-            LTAClient::with_api_key(api_key, "http://datamall2.mytransport.sg/ltaodataservice")
-                .unwrap();
-        client
+        LTAClient::with_api_key(api_key, "http://datamall2.mytransport.sg/ltaodataservice").unwrap()
     }
 
     #[test]
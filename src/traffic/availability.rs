@@ -0,0 +1,567 @@
+//! Time series of carpark availability, recorded from `get_carpark_avail`
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
+
+use crate::models::traffic::carpark_avail::{CarPark, LotType};
+use crate::utils::SGT;
+use crate::LTAResult;
+
+/// Number of carparks returned per page of `get_carpark_avail`
+pub const CARPARK_PAGE_SIZE: u32 = 500;
+
+/// Default interval after which an unchanged value is recorded again
+pub const DEFAULT_HEARTBEAT: Duration = Duration::from_secs(15 * 60);
+
+/// Code of a lot type, as used by the API
+pub fn lot_type_code(lot_type: &LotType) -> &'static str {
+    match lot_type {
+        LotType::C => "C",
+        LotType::L => "L",
+        LotType::Y => "Y",
+        LotType::H => "H",
+        LotType::Unknown => "U",
+    }
+}
+
+/// Inverse of [`lot_type_code`]
+pub fn lot_type_from_code(code: &str) -> LotType {
+    match code {
+        "C" => LotType::C,
+        "L" => LotType::L,
+        "Y" => LotType::Y,
+        "H" => LotType::H,
+        _ => LotType::Unknown,
+    }
+}
+
+/// Available lots of a carpark at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvailabilitySample {
+    /// In SGT
+    pub at: OffsetDateTime,
+    pub avail_lots: u32,
+}
+
+/// Carpark ID and lot type code
+type SeriesKey = (String, &'static str);
+
+fn series_key(carpark_id: &str, lot_type: &LotType) -> SeriesKey {
+    (carpark_id.to_string(), lot_type_code(lot_type))
+}
+
+/// Lines look like `<unix timestamp>,<carpark_id>,<lot type>,<available lots>`
+fn parse_line(line: &str) -> Option<(SeriesKey, AvailabilitySample)> {
+    let (ts, rest) = line.trim_end().split_once(',')?;
+    let mut fields = rest.rsplitn(3, ',');
+    let avail_lots = fields.next()?.parse().ok()?;
+    let lot_type = lot_type_from_code(fields.next()?);
+    let carpark_id = fields.next()?;
+
+    let at = OffsetDateTime::from_unix_timestamp(ts.parse().ok()?)
+        .ok()?
+        .to_offset(SGT);
+    Some((
+        series_key(carpark_id, &lot_type),
+        AvailabilitySample { at, avail_lots },
+    ))
+}
+
+fn format_line(carpark_id: &str, code: &str, sample: &AvailabilitySample) -> String {
+    format!(
+        "{},{},{},{}\n",
+        sample.at.unix_timestamp(),
+        carpark_id,
+        code,
+        sample.avail_lots
+    )
+}
+
+/// Outcome of recording one round of availability
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordReport {
+    /// File that was appended to, `None` if nothing changed
+    pub path: Option<PathBuf>,
+
+    /// Number of values that were written
+    pub written: usize,
+
+    /// Number of values left out because they have not changed since the last one recorded
+    pub unchanged: usize,
+}
+
+/// Appends carpark availability to `<root>/<YYYY-MM-DD>.csv`, one file per day in SGT, with
+/// lines of `<unix timestamp>,<carpark_id>,<lot type>,<available lots>`.
+///
+/// To keep weeks of history small, a value is only written when it differs from the last one
+/// recorded for the carpark and lot type, or when the last one is older than the heartbeat.
+/// A value therefore holds until the next one, and a gap longer than the heartbeat means
+/// that the carpark was not reported
+#[derive(Debug, Clone)]
+pub struct AvailabilityRecorder {
+    root: PathBuf,
+    carpark_ids: Vec<String>,
+    heartbeat: Duration,
+    last: Option<HashMap<SeriesKey, AvailabilitySample>>,
+}
+
+impl AvailabilityRecorder {
+    /// Records every carpark into `root`, with a heartbeat of [`DEFAULT_HEARTBEAT`]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        AvailabilityRecorder {
+            root: root.into(),
+            carpark_ids: Vec::new(),
+            heartbeat: DEFAULT_HEARTBEAT,
+            last: None,
+        }
+    }
+
+    /// Only records `carpark_ids`
+    pub fn carparks<S: Into<String>>(mut self, carpark_ids: impl IntoIterator<Item = S>) -> Self {
+        self.carpark_ids = carpark_ids.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Interval after which an unchanged value is recorded again
+    pub fn heartbeat(mut self, heartbeat: Duration) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn carpark_ids(&self) -> &[String] {
+        &self.carpark_ids
+    }
+
+    /// Path of the file that `date` is stored in
+    pub fn path_for(&self, date: Date) -> PathBuf {
+        day_file(&self.root, date)
+    }
+
+    /// Appends the availability of `carparks` at `at`
+    pub fn record(&mut self, carparks: &[CarPark], at: OffsetDateTime) -> LTAResult<RecordReport> {
+        let at = at.to_offset(SGT);
+        let root = &self.root;
+        let last = self.last.get_or_insert_with(|| latest_stored(root));
+
+        // Only remembered once written, so a failed write is retried next round
+        let mut pending = HashMap::new();
+        let mut report = RecordReport::default();
+        let mut lines = String::new();
+        for carpark in carparks {
+            if !self.carpark_ids.is_empty() && !self.carpark_ids.contains(&carpark.carpark_id) {
+                continue;
+            }
+
+            let key = series_key(&carpark.carpark_id, &carpark.lot_type);
+            let sample = AvailabilitySample {
+                at,
+                avail_lots: carpark.avail_lots,
+            };
+            let unchanged = pending
+                .get(&key)
+                .or_else(|| last.get(&key))
+                .is_some_and(|prev| {
+                    prev.avail_lots == sample.avail_lots && at - prev.at < self.heartbeat
+                });
+            if unchanged {
+                report.unchanged += 1;
+                continue;
+            }
+
+            lines.push_str(&format_line(&key.0, key.1, &sample));
+            pending.insert(key, sample);
+            report.written += 1;
+        }
+
+        if report.written > 0 {
+            let path = day_file(&self.root, at.date());
+            fs::create_dir_all(&self.root)?;
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?
+                .write_all(lines.as_bytes())?;
+            last.extend(pending);
+            report.path = Some(path);
+        }
+
+        Ok(report)
+    }
+}
+
+fn day_file(root: &Path, date: Date) -> PathBuf {
+    let day = date
+        .format(format_description!("[year]-[month]-[day]"))
+        .unwrap_or_default();
+    root.join(format!("{}.csv", day))
+}
+
+/// Last value of every series in the latest day file, so that a restarted recorder carries on
+/// where it stopped
+fn latest_stored(root: &Path) -> HashMap<SeriesKey, AvailabilitySample> {
+    let latest = fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "csv"))
+        .max();
+
+    latest
+        .and_then(|p| fs::read_to_string(p).ok())
+        .map(|csv| csv.lines().filter_map(parse_line).collect())
+        .unwrap_or_default()
+}
+
+/// Summary of a series over a time range
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvailabilityTrend {
+    pub from: OffsetDateTime,
+    pub to: OffsetDateTime,
+
+    /// Number of recorded values within the range
+    pub samples: usize,
+    pub min: u32,
+    pub max: u32,
+
+    /// Mean weighted by how long each value held
+    pub mean: f64,
+    pub first: u32,
+    pub last: u32,
+
+    /// Least squares slope, in lots per hour
+    pub slope_per_hour: f64,
+
+    /// Estimated share of lots in use over the range. The API does not report capacity, so
+    /// it is taken as the most lots ever recorded as available. `None` if that is 0
+    pub occupancy: Option<f64>,
+}
+
+/// Recorded availability, grouped by carpark and lot type and ordered by time
+#[derive(Debug, Clone, Default)]
+pub struct AvailabilityHistory {
+    series: HashMap<SeriesKey, Vec<AvailabilitySample>>,
+}
+
+impl AvailabilityHistory {
+    /// Reads the days from `from` to `to` of an [`AvailabilityRecorder`] archive. Days without
+    /// a file are skipped
+    pub fn read(root: impl AsRef<Path>, from: Date, to: Date) -> LTAResult<Self> {
+        let mut history = AvailabilityHistory::default();
+        let mut day = Some(from);
+
+        while let Some(date) = day.filter(|d| *d <= to) {
+            match fs::read_to_string(day_file(root.as_ref(), date)) {
+                Ok(csv) => history.extend_from_csv(&csv),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            day = date.next_day();
+        }
+
+        Ok(history)
+    }
+
+    /// Parses lines written by an [`AvailabilityRecorder`], skipping malformed ones
+    pub fn from_csv(csv: &str) -> Self {
+        let mut history = AvailabilityHistory::default();
+        history.extend_from_csv(csv);
+        history
+    }
+
+    fn extend_from_csv(&mut self, csv: &str) {
+        for (key, sample) in csv.lines().filter_map(parse_line) {
+            self.insert(key, sample);
+        }
+    }
+
+    /// Adds the availability of `carparks` at `at`
+    pub fn record(&mut self, carparks: &[CarPark], at: OffsetDateTime) {
+        for carpark in carparks {
+            self.push(
+                &carpark.carpark_id,
+                &carpark.lot_type,
+                AvailabilitySample {
+                    at: at.to_offset(SGT),
+                    avail_lots: carpark.avail_lots,
+                },
+            );
+        }
+    }
+
+    pub fn push(&mut self, carpark_id: &str, lot_type: &LotType, sample: AvailabilitySample) {
+        self.insert(series_key(carpark_id, lot_type), sample);
+    }
+
+    fn insert(&mut self, key: SeriesKey, sample: AvailabilitySample) {
+        let series = self.series.entry(key).or_default();
+        match series.last() {
+            Some(last) if last.at > sample.at => {
+                let i = series.partition_point(|s| s.at <= sample.at);
+                series.insert(i, sample);
+            }
+            _ => series.push(sample),
+        }
+    }
+
+    /// Number of carpark and lot type pairs
+    pub fn len(&self) -> usize {
+        self.series.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+
+    /// Every recorded carpark and lot type pair, in no particular order
+    pub fn series_keys(&self) -> impl Iterator<Item = (&str, LotType)> + '_ {
+        self.series
+            .keys()
+            .map(|(id, code)| (id.as_str(), lot_type_from_code(code)))
+    }
+
+    /// Every recorded value of a carpark and lot type
+    pub fn series(&self, carpark_id: &str, lot_type: &LotType) -> &[AvailabilitySample] {
+        self.series
+            .get(&series_key(carpark_id, lot_type))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Values recorded from `from` to `to`, inclusive
+    pub fn range(
+        &self,
+        carpark_id: &str,
+        lot_type: &LotType,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> &[AvailabilitySample] {
+        let series = self.series(carpark_id, lot_type);
+        let start = series.partition_point(|s| s.at < from);
+        let end = series.partition_point(|s| s.at <= to);
+        &series[start..end.max(start)]
+    }
+
+    /// Last value recorded at or before `at`
+    pub fn value_at(
+        &self,
+        carpark_id: &str,
+        lot_type: &LotType,
+        at: OffsetDateTime,
+    ) -> Option<u32> {
+        let series = self.series(carpark_id, lot_type);
+        let i = series.partition_point(|s| s.at <= at);
+        i.checked_sub(1).map(|i| series[i].avail_lots)
+    }
+
+    /// Summarises a carpark and lot type from `from` to `to`. The value recorded before `from`
+    /// is carried into the range. `None` if there is no value to summarise
+    pub fn trend(
+        &self,
+        carpark_id: &str,
+        lot_type: &LotType,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> Option<AvailabilityTrend> {
+        let in_range = self.range(carpark_id, lot_type, from, to);
+        let carried = self
+            .value_at(carpark_id, lot_type, from)
+            .filter(|_| in_range.first().is_none_or(|s| s.at > from))
+            .map(|avail_lots| AvailabilitySample {
+                at: from,
+                avail_lots,
+            });
+
+        let points = carried.iter().chain(in_range).copied().collect::<Vec<_>>();
+        let start = points.first()?.at;
+        let hours = |at: OffsetDateTime| (at - start).as_seconds_f64() / 3600.0;
+
+        let span = hours(to);
+        let mean = if span > 0.0 {
+            points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let until = points.get(i + 1).map_or(to, |n| n.at);
+                    p.avail_lots as f64 * (hours(until) - hours(p.at))
+                })
+                .sum::<f64>()
+                / span
+        } else {
+            points.iter().map(|p| p.avail_lots as f64).sum::<f64>() / points.len() as f64
+        };
+
+        let xy = points
+            .iter()
+            .map(|p| (hours(p.at), p.avail_lots as f64))
+            .collect::<Vec<_>>();
+        let (slope_per_hour, _) = least_squares(&xy)?;
+
+        let capacity = self
+            .series(carpark_id, lot_type)
+            .iter()
+            .map(|s| s.avail_lots)
+            .max()
+            .unwrap_or_default();
+
+        Some(AvailabilityTrend {
+            from,
+            to,
+            samples: in_range.len(),
+            min: points.iter().map(|p| p.avail_lots).min()?,
+            max: points.iter().map(|p| p.avail_lots).max()?,
+            mean,
+            first: points.first()?.avail_lots,
+            last: points.last()?.avail_lots,
+            slope_per_hour,
+            occupancy: (capacity > 0).then(|| (1.0 - mean / capacity as f64).clamp(0.0, 1.0)),
+        })
+    }
+}
+
+/// Slope and intercept of the line fitted to `(x, y)` points. The slope is 0 when every point
+/// has the same `x`
+pub(crate) fn least_squares(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    if points.is_empty() {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let (cov, var) = points.iter().fold((0.0, 0.0), |(cov, var), p| {
+        let dx = p.0 - mean_x;
+        (cov + dx * (p.1 - mean_y), var + dx * dx)
+    });
+
+    let slope = if var > 0.0 { cov / var } else { 0.0 };
+    Some((slope, mean_y - slope * mean_x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::traffic::carpark_avail::Agency;
+    use time::macros::{date, datetime};
+
+    fn carpark(id: &str, lot_type: LotType, avail_lots: u32) -> CarPark {
+        CarPark {
+            carpark_id: id.to_string(),
+            area: String::new(),
+            dev: String::new(),
+            coords: None,
+            avail_lots,
+            lot_type,
+            agency: Agency::HDB,
+        }
+    }
+
+    #[test]
+    fn record_only_changes() -> LTAResult<()> {
+        let root = std::env::temp_dir().join(format!("lta-carpark-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let mut recorder = AvailabilityRecorder::new(&root).carparks(["1", "2"]);
+        let round = |lots: u32| {
+            [
+                carpark("1", LotType::C, lots),
+                carpark("1", LotType::Y, 5),
+                carpark("3", LotType::C, 9),
+            ]
+        };
+
+        let report = recorder.record(&round(10), datetime!(2024-03-01 08:00 +8))?;
+        assert_eq!((report.written, report.unchanged), (2, 0));
+        assert_eq!(report.path, Some(root.join("2024-03-01.csv")));
+
+        let report = recorder.record(&round(8), datetime!(2024-03-01 08:01 +8))?;
+        assert_eq!((report.written, report.unchanged), (1, 1));
+
+        // A restarted recorder picks up the last values
+        let mut recorder = AvailabilityRecorder::new(&root).carparks(["1"]);
+        let report = recorder.record(&round(8), datetime!(2024-03-01 08:14 +8))?;
+        assert_eq!(
+            (report.written, report.unchanged, report.path),
+            (0, 2, None)
+        );
+
+        // The heartbeat has passed for `Y`
+        let report = recorder.record(&round(8), datetime!(2024-03-01 08:15 +8))?;
+        assert_eq!((report.written, report.unchanged), (1, 1));
+
+        let history =
+            AvailabilityHistory::read(&root, date!(2024 - 02 - 29), date!(2024 - 03 - 02))?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.series("1", &LotType::C).len(), 2);
+        assert_eq!(history.series("1", &LotType::Y).len(), 2);
+        assert!(history.series("3", &LotType::C).is_empty());
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn record_after_failed_write() -> LTAResult<()> {
+        let root = std::env::temp_dir().join(format!("lta-carpark-fail-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        // The root cannot be created while a file is in the way
+        fs::write(&root, b"not a directory")?;
+        let mut recorder = AvailabilityRecorder::new(&root);
+        let round = [carpark("1", LotType::C, 10)];
+        assert!(recorder
+            .record(&round, datetime!(2024-03-01 08:00 +8))
+            .is_err());
+
+        fs::remove_file(&root)?;
+        let report = recorder.record(&round, datetime!(2024-03-01 08:01 +8))?;
+        assert_eq!((report.written, report.unchanged), (1, 0));
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn range_and_trend() {
+        let history = AvailabilityHistory::from_csv(
+            "1709251200,A,C,100\n\
+             1709254800,A,C,80\n\
+             1709258400,A,C,40\n\
+             malformed\n\
+             1709262000,A,C,40\n\
+             1709262000,B,C,5\n",
+        );
+        let from = datetime!(2024-03-01 09:30 +8);
+        let to = datetime!(2024-03-01 11:00 +8);
+
+        assert_eq!(history.range("A", &LotType::C, from, to).len(), 2);
+        assert_eq!(history.value_at("A", &LotType::C, from), Some(80));
+        assert_eq!(
+            history.value_at("A", &LotType::C, datetime!(2024-03-01 07:00 +8)),
+            None
+        );
+
+        // 80 for half an hour, then 40 for an hour
+        let trend = history.trend("A", &LotType::C, from, to).unwrap();
+        assert_eq!(trend.samples, 2);
+        assert_eq!(
+            (trend.min, trend.max, trend.first, trend.last),
+            (40, 80, 80, 40)
+        );
+        assert!((trend.mean - (80.0 * 0.5 + 40.0) / 1.5).abs() < 1e-9);
+        assert!(trend.slope_per_hour < 0.0);
+        assert!((trend.occupancy.unwrap() - (1.0 - trend.mean / 100.0)).abs() < 1e-9);
+
+        assert!(history.trend("A", &LotType::Y, from, to).is_none());
+    }
+}
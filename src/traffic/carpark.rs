@@ -0,0 +1,290 @@
+//! Offline queries over the carparks returned by `get_carpark_avail`
+
+use std::cmp::Ordering;
+
+use crate::geo::{Located, Neighbour, SpatialIndex};
+use crate::models::traffic::carpark_avail::{Agency, CarPark, LotType};
+use crate::models::utils::Coordinates;
+
+/// Corners of a box around Singapore, including the offshore islands
+const SG_MIN: (f64, f64) = (1.15, 103.55);
+const SG_MAX: (f64, f64) = (1.50, 104.10);
+
+/// Returns `true` if the coordinates are finite and within Singapore
+pub fn is_valid_location(c: &Coordinates) -> bool {
+    c.lat.is_finite()
+        && c.long.is_finite()
+        && (SG_MIN.0..=SG_MAX.0).contains(&c.lat)
+        && (SG_MIN.1..=SG_MAX.1).contains(&c.long)
+}
+
+/// Parses a location field, formatted as `"<lat> <long>"`. Returns `None` if it is
+/// malformed or outside Singapore
+pub fn parse_location(s: &str) -> Option<Coordinates> {
+    let mut parts = s.split_whitespace();
+    let lat = parts.next()?.parse().ok()?;
+    let long = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let c = Coordinates::new(lat, long);
+    is_valid_location(&c).then_some(c)
+}
+
+/// Validated location of a carpark. `None` if it is missing or outside Singapore
+pub fn carpark_location(carpark: &CarPark) -> Option<Coordinates> {
+    carpark.coords.clone().filter(is_valid_location)
+}
+
+/// Order of the results of a [`CarParkFinder`] query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortBy {
+    /// Closest first
+    #[default]
+    Distance,
+
+    /// Most available lots first, ties are broken by distance
+    Availability,
+}
+
+/// Filters applied to a [`CarParkFinder`] query
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CarParkQuery {
+    /// `None` matches every lot type
+    pub lot_type: Option<LotType>,
+
+    /// `None` matches every agency
+    pub agency: Option<Agency>,
+
+    /// Minimum number of available lots
+    pub min_lots: u32,
+
+    pub sort: SortBy,
+}
+
+impl CarParkQuery {
+    pub fn matches(&self, carpark: &CarPark) -> bool {
+        self.lot_type
+            .as_ref()
+            .is_none_or(|t| *t == carpark.lot_type)
+            && self.agency.as_ref().is_none_or(|a| *a == carpark.agency)
+            && carpark.avail_lots >= self.min_lots
+    }
+}
+
+/// A carpark with its distance from the query point
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarParkMatch<'a> {
+    pub carpark: &'a CarPark,
+    pub location: &'a Coordinates,
+
+    /// Haversine distance, in metres
+    pub distance: f64,
+}
+
+#[derive(Debug, Clone)]
+struct LocatedCarPark {
+    carpark: CarPark,
+    location: Coordinates,
+}
+
+impl Located for LocatedCarPark {
+    fn coordinates(&self) -> Coordinates {
+        self.location.clone()
+    }
+}
+
+/// Spatial index over carparks. Each lot type of a carpark is a separate entry of
+/// `get_carpark_avail`, so a carpark can match a query more than once unless `lot_type` is set
+#[derive(Debug, Clone)]
+pub struct CarParkFinder {
+    index: SpatialIndex<LocatedCarPark>,
+    unlocated: Vec<CarPark>,
+}
+
+impl CarParkFinder {
+    pub fn new(carparks: Vec<CarPark>) -> Self {
+        let (located, unlocated): (Vec<_>, Vec<_>) = carparks
+            .into_iter()
+            .map(|carpark| match carpark_location(&carpark) {
+                Some(location) => Ok(LocatedCarPark { carpark, location }),
+                None => Err(carpark),
+            })
+            .partition(Result::is_ok);
+
+        CarParkFinder {
+            index: SpatialIndex::new(located.into_iter().flatten().collect()),
+            unlocated: unlocated.into_iter().filter_map(Result::err).collect(),
+        }
+    }
+
+    /// Number of carparks with a valid location
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Carparks left out of the index because their location is missing or invalid
+    pub fn unlocated(&self) -> &[CarPark] {
+        &self.unlocated
+    }
+
+    /// Returns the matching carparks within `radius` metres of `center`
+    pub fn within(
+        &self,
+        center: &Coordinates,
+        radius: f64,
+        query: &CarParkQuery,
+    ) -> Vec<CarParkMatch<'_>> {
+        let mut found = self
+            .index
+            .within(center, radius)
+            .into_iter()
+            .filter(|n| query.matches(&n.item.carpark))
+            .map(to_match)
+            .collect::<Vec<_>>();
+        sort(&mut found, query.sort);
+        found
+    }
+
+    /// Returns the `k` matching carparks closest to `center`
+    pub fn nearest(
+        &self,
+        center: &Coordinates,
+        k: usize,
+        query: &CarParkQuery,
+    ) -> Vec<CarParkMatch<'_>> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        // Widen the search until enough of the closest carparks pass the filters
+        let mut n = k;
+        let mut found = loop {
+            let neighbours = self.index.nearest(center, n);
+            let exhausted = neighbours.len() < n;
+            let found = neighbours
+                .into_iter()
+                .filter(|n| query.matches(&n.item.carpark))
+                .map(to_match)
+                .collect::<Vec<_>>();
+
+            if found.len() >= k || exhausted {
+                break found;
+            }
+            n = n.saturating_mul(4);
+        };
+
+        found.truncate(k);
+        sort(&mut found, query.sort);
+        found
+    }
+}
+
+fn to_match(n: Neighbour<'_, LocatedCarPark>) -> CarParkMatch<'_> {
+    CarParkMatch {
+        carpark: &n.item.carpark,
+        location: &n.item.location,
+        distance: n.distance,
+    }
+}
+
+fn sort(found: &mut [CarParkMatch<'_>], sort_by: SortBy) {
+    found.sort_by(|a, b| {
+        let by_lots = match sort_by {
+            SortBy::Distance => Ordering::Equal,
+            SortBy::Availability => b.carpark.avail_lots.cmp(&a.carpark.avail_lots),
+        };
+        by_lots.then(a.distance.total_cmp(&b.distance))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn carpark(id: &str, lat: f64, lots: u32, lot_type: LotType, agency: Agency) -> CarPark {
+        CarPark {
+            carpark_id: id.to_string(),
+            area: String::new(),
+            dev: format!("Carpark {}", id),
+            coords: Some(Coordinates::new(lat, 103.85)),
+            avail_lots: lots,
+            lot_type,
+            agency,
+        }
+    }
+
+    fn finder() -> CarParkFinder {
+        let mut missing = carpark("X", 0.0, 50, LotType::C, Agency::HDB);
+        missing.coords = None;
+
+        CarParkFinder::new(vec![
+            carpark("1", 1.300, 10, LotType::C, Agency::HDB),
+            carpark("1", 1.300, 3, LotType::Y, Agency::HDB),
+            carpark("2", 1.302, 80, LotType::C, Agency::URA),
+            carpark("3", 1.305, 0, LotType::C, Agency::LTA),
+            carpark("4", 1.320, 200, LotType::C, Agency::HDB),
+            carpark("5", 0.0, 200, LotType::C, Agency::HDB),
+            missing,
+        ])
+    }
+
+    #[test]
+    fn locations() {
+        assert_eq!(
+            parse_location("1.29375 103.85718"),
+            Some(Coordinates::new(1.29375, 103.85718))
+        );
+        assert_eq!(parse_location("1.29375"), None);
+        assert_eq!(parse_location("0 0"), None);
+        assert_eq!(parse_location("1.3 103.8 1"), None);
+        assert_eq!(parse_location("NaN 103.8"), None);
+
+        let finder = finder();
+        assert_eq!(finder.len(), 5);
+        assert_eq!(finder.unlocated().len(), 2);
+    }
+
+    #[test]
+    fn radius_and_nearest_with_filters() {
+        let finder = finder();
+        let center = Coordinates::new(1.300, 103.85);
+
+        let cars = CarParkQuery {
+            lot_type: Some(LotType::C),
+            min_lots: 1,
+            ..Default::default()
+        };
+        let ids = |found: Vec<CarParkMatch<'_>>| {
+            found
+                .iter()
+                .map(|m| m.carpark.carpark_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(finder.within(&center, 1_000.0, &cars)), ["1", "2"]);
+        assert_eq!(
+            finder.within(&center, 1_000.0, &Default::default()).len(),
+            4
+        );
+
+        let by_lots = CarParkQuery {
+            sort: SortBy::Availability,
+            ..cars.clone()
+        };
+        assert_eq!(ids(finder.within(&center, 1_000.0, &by_lots)), ["2", "1"]);
+
+        // The closest car lots that pass the filters are further away than `k` entries
+        assert_eq!(ids(finder.nearest(&center, 3, &cars)), ["1", "2", "4"]);
+        let hdb = CarParkQuery {
+            agency: Some(Agency::HDB),
+            ..by_lots
+        };
+        assert_eq!(ids(finder.nearest(&center, 5, &hdb)), ["4", "1"]);
+    }
+}
@@ -0,0 +1,431 @@
+//! Congestion index from the speed bands returned by `get_traffic_speed_band`
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use time::OffsetDateTime;
+
+use super::flow::category_rank;
+use crate::geo::haversine;
+use crate::models::traffic::prelude::{RoadCategory, TrafficSpeedBand};
+use crate::models::utils::Coordinates;
+
+/// Slowest and fastest speed bands
+const SLOWEST_BAND: u32 = 1;
+const FASTEST_BAND: u32 = 8;
+
+/// Default number of bands a link has to drop by between polls to be flagged
+pub const DEFAULT_SHARP_DROP: u32 = 3;
+
+/// Congestion of one link, from 0 when in the fastest band to 1 when in the slowest. `None`
+/// for bands outside 1 to 8
+pub fn link_congestion(speed_band: u32) -> Option<f64> {
+    (SLOWEST_BAND..=FASTEST_BAND)
+        .contains(&speed_band)
+        .then(|| (FASTEST_BAND - speed_band) as f64 / (FASTEST_BAND - SLOWEST_BAND) as f64)
+}
+
+fn start(band: &TrafficSpeedBand) -> Coordinates {
+    Coordinates::new(band.start_lat, band.start_lon)
+}
+
+fn end(band: &TrafficSpeedBand) -> Coordinates {
+    Coordinates::new(band.end_lat, band.end_lon)
+}
+
+fn midpoint(band: &TrafficSpeedBand) -> Coordinates {
+    Coordinates::new(
+        (band.start_lat + band.end_lat) / 2.0,
+        (band.start_lon + band.end_lon) / 2.0,
+    )
+}
+
+/// Congestion of a group of links, weighted by their length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CongestionIndex {
+    /// Number of links with a valid speed band
+    pub links: usize,
+
+    /// Total length of the links, in metres
+    pub length: f64,
+
+    /// From 0, free flowing, to 1, every link in the slowest band
+    pub index: f64,
+}
+
+impl CongestionIndex {
+    /// `None` if none of the links has a valid speed band. Links of zero length are only
+    /// counted when every link has zero length
+    pub fn new<'a>(bands: impl IntoIterator<Item = &'a TrafficSpeedBand>) -> Option<Self> {
+        let (mut links, mut length, mut weighted, mut unweighted) = (0, 0.0, 0.0, 0.0);
+        for band in bands {
+            let Some(congestion) = link_congestion(band.speed_band) else {
+                continue;
+            };
+            let link_length = haversine(&start(band), &end(band));
+            links += 1;
+            length += link_length;
+            weighted += congestion * link_length;
+            unweighted += congestion;
+        }
+
+        let index = match (links, length > 0.0) {
+            (0, _) => return None,
+            (_, true) => weighted / length,
+            (n, false) => unweighted / n as f64,
+        };
+        Some(CongestionIndex {
+            links,
+            length,
+            index,
+        })
+    }
+}
+
+/// A link whose speed band dropped between two polls
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedDrop {
+    pub link_id: u64,
+    pub road_name: String,
+    pub road_category: RoadCategory,
+    pub before: u32,
+    pub after: u32,
+}
+
+impl SpeedDrop {
+    /// Number of bands dropped
+    pub fn bands(&self) -> u32 {
+        self.before - self.after
+    }
+}
+
+/// Speed bands of one poll
+#[derive(Debug, Clone)]
+pub struct CongestionSnapshot {
+    pub at: OffsetDateTime,
+    bands: Vec<TrafficSpeedBand>,
+}
+
+impl CongestionSnapshot {
+    pub fn new(bands: Vec<TrafficSpeedBand>, at: OffsetDateTime) -> Self {
+        CongestionSnapshot { at, bands }
+    }
+
+    pub fn bands(&self) -> &[TrafficSpeedBand] {
+        &self.bands
+    }
+
+    /// Index over every link
+    pub fn overall(&self) -> Option<CongestionIndex> {
+        CongestionIndex::new(&self.bands)
+    }
+
+    /// Index of every road, ordered by road name
+    pub fn by_road(&self) -> BTreeMap<String, CongestionIndex> {
+        let mut roads: BTreeMap<&str, Vec<&TrafficSpeedBand>> = BTreeMap::new();
+        for band in &self.bands {
+            roads.entry(&band.road_name).or_default().push(band);
+        }
+
+        roads
+            .into_iter()
+            .filter_map(|(road, bands)| Some((road.to_string(), CongestionIndex::new(bands)?)))
+            .collect()
+    }
+
+    /// Index of every road category, in the order of `RoadCategory`
+    pub fn by_category(&self) -> Vec<(RoadCategory, CongestionIndex)> {
+        let mut categories: Vec<(&RoadCategory, Vec<&TrafficSpeedBand>)> = Vec::new();
+        for band in &self.bands {
+            match categories
+                .iter_mut()
+                .find(|(c, _)| **c == band.road_category)
+            {
+                Some((_, bands)) => bands.push(band),
+                None => categories.push((&band.road_category, vec![band])),
+            }
+        }
+        categories.sort_by_key(|(c, _)| category_rank(c));
+
+        categories
+            .into_iter()
+            .filter_map(|(c, bands)| Some((c.clone(), CongestionIndex::new(bands)?)))
+            .collect()
+    }
+
+    /// Index of the links whose midpoint is inside the box spanned by two opposite corners
+    pub fn within_bbox(&self, a: &Coordinates, b: &Coordinates) -> Option<CongestionIndex> {
+        let (min_lat, max_lat) = (a.lat.min(b.lat), a.lat.max(b.lat));
+        let (min_long, max_long) = (a.long.min(b.long), a.long.max(b.long));
+        CongestionIndex::new(self.bands.iter().filter(|band| {
+            let m = midpoint(band);
+            (min_lat..=max_lat).contains(&m.lat) && (min_long..=max_long).contains(&m.long)
+        }))
+    }
+
+    /// Index of the links whose midpoint is inside a polygon, given by its vertices
+    pub fn within_region(&self, region: &[Coordinates]) -> Option<CongestionIndex> {
+        CongestionIndex::new(
+            self.bands
+                .iter()
+                .filter(|band| in_polygon(&midpoint(band), region)),
+        )
+    }
+
+    /// Links whose speed band dropped by at least `min_drop` bands since `previous`
+    pub fn drops(&self, previous: &CongestionSnapshot, min_drop: u32) -> Vec<SpeedDrop> {
+        drops(&band_of_links(&previous.bands), &self.bands, min_drop)
+    }
+}
+
+fn band_of_links(bands: &[TrafficSpeedBand]) -> HashMap<u64, u32> {
+    bands.iter().map(|b| (b.link_id, b.speed_band)).collect()
+}
+
+fn drops(
+    previous: &HashMap<u64, u32>,
+    bands: &[TrafficSpeedBand],
+    min_drop: u32,
+) -> Vec<SpeedDrop> {
+    let mut found = bands
+        .iter()
+        .filter(|b| link_congestion(b.speed_band).is_some())
+        .filter_map(|b| {
+            let before = *previous.get(&b.link_id)?;
+            let dropped = before
+                .checked_sub(b.speed_band)
+                .is_some_and(|d| d >= min_drop.max(1));
+            (link_congestion(before).is_some() && dropped).then(|| SpeedDrop {
+                link_id: b.link_id,
+                road_name: b.road_name.clone(),
+                road_category: b.road_category.clone(),
+                before,
+                after: b.speed_band,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    found.sort_by(|a, b| b.bands().cmp(&a.bands()).then(a.link_id.cmp(&b.link_id)));
+    found
+}
+
+/// Ray casting, points on an edge may fall either side
+fn in_polygon(p: &Coordinates, polygon: &[Coordinates]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+    for (i, a) in polygon.iter().enumerate() {
+        let b = &polygon[j];
+        if (a.lat > p.lat) != (b.lat > p.lat)
+            && p.long < (b.long - a.long) * (p.lat - a.lat) / (b.lat - a.lat) + a.long
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Index at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexPoint {
+    pub at: OffsetDateTime,
+    pub index: CongestionIndex,
+}
+
+/// Tracks the overall, per road and per category indices across successive polls, keeping
+/// the latest `retain` points of each. Only the speed band of each link of the last poll is
+/// kept, so it stays small when fed every poll of a day
+#[derive(Debug, Clone)]
+pub struct CongestionTracker {
+    retain: usize,
+    min_drop: u32,
+    previous: HashMap<u64, u32>,
+    overall: VecDeque<IndexPoint>,
+    roads: HashMap<String, VecDeque<IndexPoint>>,
+    categories: Vec<(RoadCategory, VecDeque<IndexPoint>)>,
+}
+
+impl CongestionTracker {
+    /// Flags drops of at least [`DEFAULT_SHARP_DROP`] bands
+    pub fn new(retain: usize) -> Self {
+        CongestionTracker {
+            retain: retain.max(1),
+            min_drop: DEFAULT_SHARP_DROP,
+            previous: HashMap::new(),
+            overall: VecDeque::new(),
+            roads: HashMap::new(),
+            categories: Vec::new(),
+        }
+    }
+
+    /// Number of bands a link has to drop by between polls to be flagged, at least 1
+    pub fn min_drop(mut self, min_drop: u32) -> Self {
+        self.min_drop = min_drop.max(1);
+        self
+    }
+
+    /// Records a poll and returns the links whose speed band dropped sharply since the last one
+    pub fn update(&mut self, snapshot: &CongestionSnapshot) -> Vec<SpeedDrop> {
+        let found = drops(&self.previous, &snapshot.bands, self.min_drop);
+        self.previous = band_of_links(&snapshot.bands);
+
+        let retain = self.retain;
+        let push = |series: &mut VecDeque<IndexPoint>, index| {
+            series.push_back(IndexPoint {
+                at: snapshot.at,
+                index,
+            });
+            while series.len() > retain {
+                series.pop_front();
+            }
+        };
+
+        if let Some(index) = snapshot.overall() {
+            push(&mut self.overall, index);
+        }
+        for (road, index) in snapshot.by_road() {
+            push(self.roads.entry(road).or_default(), index);
+        }
+        for (category, index) in snapshot.by_category() {
+            match self.categories.iter_mut().find(|(c, _)| *c == category) {
+                Some((_, series)) => push(series, index),
+                None => {
+                    let mut series = VecDeque::new();
+                    push(&mut series, index);
+                    self.categories.push((category, series));
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Oldest first
+    pub fn overall(&self) -> &VecDeque<IndexPoint> {
+        &self.overall
+    }
+
+    /// Oldest first, `None` if the road has not been seen
+    pub fn road(&self, road_name: &str) -> Option<&VecDeque<IndexPoint>> {
+        self.roads.get(road_name)
+    }
+
+    /// Oldest first, `None` if the category has not been seen
+    pub fn category(&self, road_category: &RoadCategory) -> Option<&VecDeque<IndexPoint>> {
+        self.categories
+            .iter()
+            .find(|(c, _)| c == road_category)
+            .map(|(_, series)| series)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn band(
+        link_id: u64,
+        road_name: &str,
+        speed_band: u32,
+        lat: f64,
+        length: f64,
+    ) -> TrafficSpeedBand {
+        TrafficSpeedBand {
+            link_id,
+            road_name: road_name.to_string(),
+            road_category: match road_name {
+                "PIE" | "CTE" => RoadCategory::Expressway,
+                _ => RoadCategory::SmallRoads,
+            },
+            speed_band,
+            min_speed: 0,
+            max_speed: 0,
+            start_lon: 103.8,
+            start_lat: lat,
+            end_lon: 103.8,
+            end_lat: lat + length,
+        }
+    }
+
+    fn snapshot(pie: u32, at: OffsetDateTime) -> CongestionSnapshot {
+        CongestionSnapshot::new(
+            vec![
+                band(1, "PIE", pie, 1.30, 0.003),
+                band(2, "PIE", 8, 1.31, 0.001),
+                band(3, "CTE", 8, 1.35, 0.002),
+                band(4, "KENT ROAD", 1, 1.40, 0.001),
+                band(5, "KENT ROAD", 0, 1.40, 0.001),
+            ],
+            at,
+        )
+    }
+
+    #[test]
+    fn weighted_by_length() {
+        let snapshot = snapshot(1, datetime!(2024-03-01 08:00 +8));
+
+        // The congested link of the PIE is three times as long as the free flowing one
+        let roads = snapshot.by_road();
+        assert!((roads["PIE"].index - 0.75).abs() < 1e-3);
+        assert_eq!(roads["CTE"].index, 0.0);
+        assert_eq!(roads["KENT ROAD"].links, 1);
+
+        let categories = snapshot.by_category();
+        assert_eq!(categories[0].0, RoadCategory::Expressway);
+        assert!((categories[0].1.index - 0.5).abs() < 1e-3);
+        assert_eq!(categories[1].1.index, 1.0);
+
+        let bbox = snapshot
+            .within_bbox(
+                &Coordinates::new(1.29, 103.7),
+                &Coordinates::new(1.32, 103.9),
+            )
+            .unwrap();
+        assert_eq!(bbox.links, 2);
+        let region = [
+            Coordinates::new(1.33, 103.7),
+            Coordinates::new(1.45, 103.7),
+            Coordinates::new(1.45, 103.9),
+        ];
+        assert_eq!(snapshot.within_region(&region).unwrap().links, 1);
+        assert!(snapshot
+            .within_bbox(&Coordinates::new(1.0, 103.0), &Coordinates::new(1.1, 103.1))
+            .is_none());
+    }
+
+    #[test]
+    fn tracks_polls_and_sharp_drops() {
+        let mut tracker = CongestionTracker::new(2);
+        assert!(tracker
+            .update(&snapshot(7, datetime!(2024-03-01 08:00 +8)))
+            .is_empty());
+        assert!(tracker
+            .update(&snapshot(6, datetime!(2024-03-01 08:05 +8)))
+            .is_empty());
+
+        let drops = tracker.update(&snapshot(2, datetime!(2024-03-01 08:10 +8)));
+        assert_eq!(drops.len(), 1);
+        assert_eq!((drops[0].link_id, drops[0].bands()), (1, 4));
+
+        let pie = tracker.road("PIE").unwrap();
+        assert_eq!(pie.len(), 2);
+        assert_eq!(pie[0].at, datetime!(2024-03-01 08:05 +8));
+        assert!(pie[1].index.index > pie[0].index.index);
+        assert_eq!(
+            tracker.category(&RoadCategory::Expressway).unwrap().len(),
+            2
+        );
+        assert!(tracker.road("CTE").is_some() && tracker.road("AYE").is_none());
+    }
+
+    #[test]
+    fn never_flag_drops() {
+        let before = snapshot(8, datetime!(2024-03-01 08:00 +8));
+        let after = snapshot(1, datetime!(2024-03-01 08:05 +8));
+        assert!(after.drops(&before, u32::MAX).is_empty());
+
+        let mut tracker = CongestionTracker::new(2).min_drop(u32::MAX);
+        tracker.update(&before);
+        assert!(tracker.update(&after).is_empty());
+    }
+}
@@ -0,0 +1,531 @@
+//! ERP charges from the rates returned by `get_erp_rates`
+
+use time::{Date, OffsetDateTime, Time};
+
+use crate::models::traffic::erp_rates::{DayType as ErpDayType, ErpRate, VehicleType, ZoneId};
+use crate::utils::{DayType, SGT};
+
+/// Day type of the rates that apply on `date`. `None` on Sundays and public holidays, when
+/// ERP does not operate
+pub fn erp_day_type(date: Date) -> Option<ErpDayType> {
+    match DayType::for_date(date) {
+        DayType::Weekday => Some(ErpDayType::Weekdays),
+        DayType::Saturday => Some(ErpDayType::Saturday),
+        DayType::SundayOrHoliday => None,
+    }
+}
+
+/// Returns `true` if `time` is within the band of `rate`. The start is inclusive and the end
+/// exclusive, and an end that is not after the start runs to midnight
+pub fn in_band(rate: &ErpRate, time: Time) -> bool {
+    match (rate.start_time, rate.end_time) {
+        (Some(start), Some(end)) if end > start => (start..end).contains(&time),
+        (Some(start), Some(_)) => time >= start,
+        _ => false,
+    }
+}
+
+/// Gantry zone passed at a point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneCrossing {
+    pub zone_id: ZoneId,
+    pub at: OffsetDateTime,
+}
+
+impl ZoneCrossing {
+    pub fn new(zone_id: ZoneId, at: OffsetDateTime) -> Self {
+        ZoneCrossing { zone_id, at }
+    }
+}
+
+/// Charge of one crossing of a trip
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossingCharge {
+    pub crossing: ZoneCrossing,
+
+    /// 0 if the zone does not charge at that time
+    pub charge_amt: f32,
+}
+
+/// Charges of the crossings of a trip, in the order they were given
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TripCharge {
+    pub crossings: Vec<CrossingCharge>,
+    pub total: f32,
+}
+
+/// Looks up ERP charges. Build it once from the full result of `get_erp_rates`.
+///
+/// A zone can have rows of several effective dates when a revision has been announced. For
+/// each zone, vehicle type and day type, only the rows of the latest effective date that is
+/// not after the date of travel are in effect
+#[derive(Debug, Clone, Default)]
+pub struct ErpCalculator {
+    rates: Vec<ErpRate>,
+}
+
+impl ErpCalculator {
+    pub fn new(rates: Vec<ErpRate>) -> Self {
+        ErpCalculator { rates }
+    }
+
+    pub fn rates(&self) -> &[ErpRate] {
+        &self.rates
+    }
+
+    /// Rows that apply to a zone and vehicle type on `date`, ordered by start time. Empty on
+    /// Sundays and public holidays
+    pub fn rates_in_effect(
+        &self,
+        zone_id: &ZoneId,
+        vehicle_type: &VehicleType,
+        date: Date,
+    ) -> Vec<&ErpRate> {
+        let Some(day_type) = erp_day_type(date) else {
+            return Vec::new();
+        };
+
+        self.effective_rows(zone_id, vehicle_type, &day_type, date)
+    }
+
+    /// Rows of the latest effective date that is not after `date`, ordered by start time
+    fn effective_rows(
+        &self,
+        zone_id: &ZoneId,
+        vehicle_type: &VehicleType,
+        day_type: &ErpDayType,
+        date: Date,
+    ) -> Vec<&ErpRate> {
+        let rows = self
+            .rates
+            .iter()
+            .filter(|r| {
+                r.zone_id == *zone_id
+                    && r.day_type == *day_type
+                    && r.vehicle_type.contains(vehicle_type)
+                    && r.effective_date <= date
+            })
+            .collect::<Vec<_>>();
+
+        let Some(effective) = rows.iter().map(|r| r.effective_date).max() else {
+            return Vec::new();
+        };
+        let mut rows = rows
+            .into_iter()
+            .filter(|r| r.effective_date == effective)
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|r| r.start_time);
+        rows
+    }
+
+    /// Row that charges a vehicle type passing a zone at `at`, if any
+    pub fn rate_at(
+        &self,
+        zone_id: &ZoneId,
+        vehicle_type: &VehicleType,
+        at: OffsetDateTime,
+    ) -> Option<&ErpRate> {
+        let at = at.to_offset(SGT);
+        self.rates_in_effect(zone_id, vehicle_type, at.date())
+            .into_iter()
+            .find(|r| in_band(r, at.time()))
+    }
+
+    /// Charge for a vehicle type passing a zone at `at`. 0 if the zone does not charge then
+    pub fn charge(&self, zone_id: &ZoneId, vehicle_type: &VehicleType, at: OffsetDateTime) -> f32 {
+        self.rate_at(zone_id, vehicle_type, at)
+            .map_or(0.0, |r| r.charge_amt)
+    }
+
+    /// Charges a vehicle type for every crossing of a trip
+    pub fn trip_charge(
+        &self,
+        vehicle_type: &VehicleType,
+        crossings: impl IntoIterator<Item = ZoneCrossing>,
+    ) -> TripCharge {
+        let crossings = crossings
+            .into_iter()
+            .map(|crossing| CrossingCharge {
+                charge_amt: self.charge(&crossing.zone_id, vehicle_type, crossing.at),
+                crossing,
+            })
+            .collect::<Vec<_>>();
+
+        TripCharge {
+            total: crossings.iter().map(|c| c.charge_amt).sum(),
+            crossings,
+        }
+    }
+
+    /// Charging schedule of every zone, vehicle type and day type on `date`
+    pub fn timetable(&self, date: Date) -> ErpTimetable {
+        let mut keys: Vec<(&ZoneId, &VehicleType, &ErpDayType)> = Vec::new();
+        for r in &self.rates {
+            for vehicle_type in &r.vehicle_type {
+                let key = (&r.zone_id, vehicle_type, &r.day_type);
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys.sort_by_cached_key(|(z, v, d)| (format!("{:?}", z), format!("{:?}", v), day_order(d)));
+
+        let schedules = keys
+            .into_iter()
+            .filter_map(|(zone_id, vehicle_type, day_type)| {
+                let rows = self.effective_rows(zone_id, vehicle_type, day_type, date);
+                let first = rows.first()?;
+                Some(ZoneSchedule {
+                    zone_id: zone_id.clone(),
+                    vehicle_type: vehicle_type.clone(),
+                    day_type: day_type.clone(),
+                    effective_date: first.effective_date,
+                    bands: rows.iter().filter_map(|r| ErpBand::new(r)).collect(),
+                })
+            })
+            .collect();
+
+        ErpTimetable { date, schedules }
+    }
+
+    /// Effective dates after `today`, earliest first
+    pub fn revision_dates(&self, today: Date) -> Vec<Date> {
+        let mut dates = self
+            .rates
+            .iter()
+            .map(|r| r.effective_date)
+            .filter(|d| *d > today)
+            .collect::<Vec<_>>();
+        dates.sort_unstable();
+        dates.dedup();
+        dates
+    }
+
+    /// Revisions that take effect after `today`, each compared with the schedule of the day
+    /// before it
+    pub fn upcoming_revisions(&self, today: Date) -> Vec<ErpRevision> {
+        self.revision_dates(today)
+            .into_iter()
+            .filter_map(|date| {
+                let before = self.timetable(date.previous_day()?);
+                Some(ErpRevision {
+                    effective_date: date,
+                    changes: before.diff(&self.timetable(date)),
+                })
+            })
+            .collect()
+    }
+}
+
+fn day_order(day_type: &ErpDayType) -> u8 {
+    match day_type {
+        ErpDayType::Weekdays => 0,
+        ErpDayType::Saturday => 1,
+        ErpDayType::Unknown => 2,
+    }
+}
+
+/// A charging band of a schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErpBand {
+    pub start_time: Time,
+    pub end_time: Time,
+    pub charge_amt: f32,
+}
+
+impl ErpBand {
+    /// `None` if the row has no start or end time
+    pub fn new(rate: &ErpRate) -> Option<Self> {
+        Some(ErpBand {
+            start_time: rate.start_time?,
+            end_time: rate.end_time?,
+            charge_amt: rate.charge_amt,
+        })
+    }
+}
+
+/// Bands charged at a zone for a vehicle type and day type
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneSchedule {
+    pub zone_id: ZoneId,
+    pub vehicle_type: VehicleType,
+    pub day_type: ErpDayType,
+
+    /// Effective date of the rows the bands come from
+    pub effective_date: Date,
+
+    /// Ordered by start time
+    pub bands: Vec<ErpBand>,
+}
+
+/// Schedules in effect on a date, ordered by zone, vehicle type and day type
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErpTimetable {
+    pub date: Date,
+    pub schedules: Vec<ZoneSchedule>,
+}
+
+impl ErpTimetable {
+    pub fn schedule(
+        &self,
+        zone_id: &ZoneId,
+        vehicle_type: &VehicleType,
+        day_type: &ErpDayType,
+    ) -> Option<&ZoneSchedule> {
+        self.schedules.iter().find(|s| {
+            s.zone_id == *zone_id && s.vehicle_type == *vehicle_type && s.day_type == *day_type
+        })
+    }
+
+    /// Bands that differ between `self` and `other`. Bands are matched by their start and
+    /// end time
+    pub fn diff<'a>(&'a self, other: &'a ErpTimetable) -> Vec<BandChange> {
+        let mut changes = Vec::new();
+        let mut keys = self.schedules.iter().collect::<Vec<_>>();
+        for s in &other.schedules {
+            if self
+                .schedule(&s.zone_id, &s.vehicle_type, &s.day_type)
+                .is_none()
+            {
+                keys.push(s);
+            }
+        }
+
+        for key in keys {
+            let bands = |t: &'a ErpTimetable| {
+                t.schedule(&key.zone_id, &key.vehicle_type, &key.day_type)
+                    .map_or(&[][..], |s| s.bands.as_slice())
+            };
+            let (before, after) = (bands(self), bands(other));
+
+            let mut times = before
+                .iter()
+                .chain(after)
+                .map(|b| (b.start_time, b.end_time))
+                .collect::<Vec<_>>();
+            times.sort_unstable();
+            times.dedup();
+
+            for (start_time, end_time) in times {
+                let charge = |bands: &[ErpBand]| {
+                    bands
+                        .iter()
+                        .find(|b| b.start_time == start_time && b.end_time == end_time)
+                        .map(|b| b.charge_amt)
+                };
+                let (before, after) = (charge(before), charge(after));
+                if before != after {
+                    changes.push(BandChange {
+                        zone_id: key.zone_id.clone(),
+                        vehicle_type: key.vehicle_type.clone(),
+                        day_type: key.day_type.clone(),
+                        start_time,
+                        end_time,
+                        before,
+                        after,
+                    });
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+/// A band that is added, removed or charged differently
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandChange {
+    pub zone_id: ZoneId,
+    pub vehicle_type: VehicleType,
+    pub day_type: ErpDayType,
+    pub start_time: Time,
+    pub end_time: Time,
+
+    /// `None` if the band is added
+    pub before: Option<f32>,
+
+    /// `None` if the band is removed
+    pub after: Option<f32>,
+}
+
+/// Changes that take effect on a date
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErpRevision {
+    pub effective_date: Date,
+    pub changes: Vec<BandChange>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::{date, datetime, time};
+
+    fn rate(
+        zone_id: ZoneId,
+        day_type: ErpDayType,
+        band: (Time, Time),
+        charge_amt: f32,
+        effective_date: Date,
+    ) -> ErpRate {
+        ErpRate {
+            vehicle_type: vec![VehicleType::PassengerCars, VehicleType::Taxis],
+            day_type,
+            start_time: Some(band.0),
+            end_time: Some(band.1),
+            zone_id,
+            charge_amt,
+            effective_date,
+        }
+    }
+
+    fn calculator() -> ErpCalculator {
+        let old = date!(2023 - 11 - 06);
+        let new = date!(2024 - 05 - 06);
+        ErpCalculator::new(vec![
+            rate(
+                ZoneId::CT1,
+                ErpDayType::Weekdays,
+                (time!(08:00), time!(08:30)),
+                1.0,
+                old,
+            ),
+            rate(
+                ZoneId::CT1,
+                ErpDayType::Weekdays,
+                (time!(08:30), time!(09:00)),
+                2.0,
+                old,
+            ),
+            rate(
+                ZoneId::CT1,
+                ErpDayType::Weekdays,
+                (time!(08:00), time!(08:30)),
+                1.5,
+                new,
+            ),
+            rate(
+                ZoneId::CT1,
+                ErpDayType::Saturday,
+                (time!(12:00), time!(12:30)),
+                0.5,
+                old,
+            ),
+            rate(
+                ZoneId::CBD,
+                ErpDayType::Weekdays,
+                (time!(08:30), time!(09:00)),
+                3.0,
+                old,
+            ),
+        ])
+    }
+
+    #[test]
+    fn effective_date_day_type_and_bands() {
+        let calculator = calculator();
+        let car = VehicleType::PassengerCars;
+
+        assert_eq!(
+            calculator.charge(&ZoneId::CT1, &car, datetime!(2024-03-01 08:15 +8)),
+            1.0
+        );
+        // The band ends before 0830
+        assert_eq!(
+            calculator.charge(&ZoneId::CT1, &car, datetime!(2024-03-01 08:30 +8)),
+            2.0
+        );
+        // UTC is converted to SGT
+        assert_eq!(
+            calculator.charge(&ZoneId::CT1, &car, datetime!(2024-03-01 00:15 UTC)),
+            1.0
+        );
+
+        // The revision replaces every weekday row of the zone
+        assert_eq!(
+            calculator.charge(&ZoneId::CT1, &car, datetime!(2024-05-06 08:15 +8)),
+            1.5
+        );
+        assert_eq!(
+            calculator.charge(&ZoneId::CT1, &car, datetime!(2024-05-06 08:45 +8)),
+            0.0
+        );
+
+        assert_eq!(
+            calculator.charge(&ZoneId::CT1, &car, datetime!(2024-03-02 12:00 +8)),
+            0.5
+        );
+        // Good Friday and other vehicle types are not charged
+        assert_eq!(
+            calculator.charge(&ZoneId::CT1, &car, datetime!(2024-03-29 08:15 +8)),
+            0.0
+        );
+        assert_eq!(
+            calculator.charge(
+                &ZoneId::CT1,
+                &VehicleType::Motorcycles,
+                datetime!(2024-03-01 08:15 +8)
+            ),
+            0.0
+        );
+    }
+
+    #[test]
+    fn trip_total() {
+        let trip = calculator().trip_charge(
+            &VehicleType::Taxis,
+            [
+                ZoneCrossing::new(ZoneId::CT1, datetime!(2024-03-01 08:25 +8)),
+                ZoneCrossing::new(ZoneId::CBD, datetime!(2024-03-01 08:35 +8)),
+                ZoneCrossing::new(ZoneId::AYT, datetime!(2024-03-01 08:40 +8)),
+            ],
+        );
+
+        let charges = trip
+            .crossings
+            .iter()
+            .map(|c| c.charge_amt)
+            .collect::<Vec<_>>();
+        assert_eq!(charges, vec![1.0, 3.0, 0.0]);
+        assert_eq!(trip.total, 4.0);
+    }
+
+    #[test]
+    fn timetable_and_revisions() {
+        let calculator = calculator();
+        let car = VehicleType::PassengerCars;
+
+        let timetable = calculator.timetable(date!(2024 - 03 - 01));
+        // CBD and CT1 for cars and taxis, CT1 on weekdays and Saturdays
+        assert_eq!(timetable.schedules.len(), 6);
+        assert_eq!(timetable.schedules[0].zone_id, ZoneId::CBD);
+        let weekdays = timetable
+            .schedule(&ZoneId::CT1, &car, &ErpDayType::Weekdays)
+            .unwrap();
+        assert_eq!(weekdays.effective_date, date!(2023 - 11 - 06));
+        assert_eq!(weekdays.bands.len(), 2);
+
+        assert_eq!(
+            calculator.revision_dates(date!(2024 - 03 - 01)),
+            vec![date!(2024 - 05 - 06)]
+        );
+        assert!(calculator
+            .upcoming_revisions(date!(2024 - 05 - 06))
+            .is_empty());
+
+        let revisions = calculator.upcoming_revisions(date!(2024 - 03 - 01));
+        assert_eq!(revisions.len(), 1);
+        let changes = revisions[0]
+            .changes
+            .iter()
+            .filter(|c| c.vehicle_type == car)
+            .map(|c| (c.zone_id.clone(), c.start_time, c.before, c.after))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                (ZoneId::CT1, time!(08:00), Some(1.0), Some(1.5)),
+                (ZoneId::CT1, time!(08:30), Some(2.0), None),
+            ]
+        );
+    }
+}
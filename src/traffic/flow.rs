@@ -0,0 +1,416 @@
+//! Decoding of the JSON file linked by the traffic flow API
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use time::macros::format_description;
+use time::Date;
+
+use crate::models::traffic::prelude::RoadCategory;
+use crate::models::utils::{Coordinates, Location};
+use crate::utils::de::{num_or_str, opt_num_or_str};
+use crate::{LTAError, LTAResult};
+
+/// A quarter of a year, formatted as `YYYYQn`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Quarter {
+    year: i32,
+    quarter: u8,
+}
+
+impl Quarter {
+    /// Returns `None` if `quarter` is not within 1 to 4
+    pub fn new(year: i32, quarter: u8) -> Option<Self> {
+        (1..=4)
+            .contains(&quarter)
+            .then_some(Quarter { year, quarter })
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn quarter(&self) -> u8 {
+        self.quarter
+    }
+}
+
+impl From<Date> for Quarter {
+    fn from(date: Date) -> Self {
+        Quarter {
+            year: date.year(),
+            quarter: (u8::from(date.month()) - 1) / 3 + 1,
+        }
+    }
+}
+
+impl fmt::Display for Quarter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}Q{}", self.year, self.quarter)
+    }
+}
+
+impl FromStr for Quarter {
+    type Err = String;
+
+    /// Accepts `2023Q4`, `2023-Q4` and `Q4 2023`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let compact = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '/'))
+            .collect::<String>()
+            .to_ascii_uppercase();
+
+        let (year, quarter) = match compact.split_once('Q') {
+            Some((year, quarter)) if !year.is_empty() => (year, quarter),
+            Some((_, rest)) => match rest.char_indices().nth(1) {
+                Some((i, _)) => (&rest[i..], &rest[..i]),
+                None => return Err(format!("Invalid quarter `{}`", s)),
+            },
+            _ => return Err(format!("Invalid quarter `{}`", s)),
+        };
+
+        year.parse()
+            .ok()
+            .zip(quarter.parse().ok())
+            .and_then(|(year, quarter)| Quarter::new(year, quarter))
+            .ok_or_else(|| format!("Invalid quarter `{}`", s))
+    }
+}
+
+impl TryFrom<String> for Quarter {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Quarter> for String {
+    fn from(quarter: Quarter) -> Self {
+        quarter.to_string()
+    }
+}
+
+/// Hourly average traffic flow of a road link
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", try_from = "RawTrafficFlow")]
+pub struct TrafficFlow {
+    pub link_id: u64,
+    pub road_name: String,
+    pub road_category: RoadCategory,
+
+    /// Quarter that the average is taken over, `None` if the file does not say
+    pub quarter: Option<Quarter>,
+
+    /// Hour of the day, from 0 to 23
+    pub hour: u8,
+
+    /// Average number of vehicles in the hour
+    pub volume: f64,
+
+    /// Start and end of the link, if present in the file
+    pub location: Option<Location>,
+}
+
+/// Shape of the records in the file
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawTrafficFlow {
+    #[serde(rename = "LinkID", alias = "LinkId", deserialize_with = "num_or_str")]
+    link_id: u64,
+    #[serde(default)]
+    road_name: String,
+    #[serde(alias = "RoadCat", default)]
+    road_category: RoadCategory,
+    #[serde(default)]
+    quarter: Option<String>,
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(alias = "HourOfDate", deserialize_with = "num_or_str")]
+    hour: u8,
+    #[serde(alias = "Flow", alias = "AvgVolume", deserialize_with = "num_or_str")]
+    volume: f64,
+    #[serde(default)]
+    location: Option<Location>,
+    #[serde(default, deserialize_with = "opt_num_or_str")]
+    start_lat: Option<f64>,
+    #[serde(default, deserialize_with = "opt_num_or_str")]
+    start_lon: Option<f64>,
+    #[serde(default, deserialize_with = "opt_num_or_str")]
+    end_lat: Option<f64>,
+    #[serde(default, deserialize_with = "opt_num_or_str")]
+    end_lon: Option<f64>,
+}
+
+impl TryFrom<RawTrafficFlow> for TrafficFlow {
+    type Error = String;
+
+    fn try_from(raw: RawTrafficFlow) -> Result<Self, Self::Error> {
+        if raw.hour > 23 {
+            return Err(format!("Invalid hour `{}`", raw.hour));
+        }
+
+        let quarter = match (raw.quarter, raw.date) {
+            (Some(q), _) => Some(q.parse()?),
+            (None, Some(d)) => Some(Quarter::from(parse_date(&d)?)),
+            (None, None) => None,
+        };
+
+        let location = raw.location.or_else(|| {
+            let (start_lat, start_lon) = (raw.start_lat?, raw.start_lon?);
+            let (end_lat, end_lon) = (raw.end_lat?, raw.end_lon?);
+            Some(Location::from_coords(
+                Coordinates::new(start_lat, start_lon),
+                Coordinates::new(end_lat, end_lon),
+            ))
+        });
+
+        Ok(TrafficFlow {
+            link_id: raw.link_id,
+            road_name: raw.road_name,
+            road_category: raw.road_category,
+            quarter,
+            hour: raw.hour,
+            volume: raw.volume,
+            location,
+        })
+    }
+}
+
+/// Accepts `YYYY-MM-DD` or an ISO 8601 timestamp
+fn parse_date(s: &str) -> Result<Date, String> {
+    let iso = format_description!("[year]-[month]-[day]");
+    s.trim()
+        .get(..10)
+        .and_then(|d| Date::parse(d, iso).ok())
+        .ok_or_else(|| format!("Invalid date `{}`", s))
+}
+
+/// Traffic flow of all the links of a road category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryFlow {
+    pub road_category: RoadCategory,
+
+    /// Number of distinct links
+    pub links: usize,
+
+    /// Number of hourly records
+    pub records: usize,
+
+    pub mean_volume: f64,
+    pub max_volume: f64,
+}
+
+/// Decoded traffic flow file, indexed by link ID
+#[derive(Debug, Clone, Default)]
+pub struct TrafficFlowDataset {
+    records: Vec<TrafficFlow>,
+    by_link: HashMap<u64, Vec<usize>>,
+}
+
+impl TrafficFlowDataset {
+    pub fn new(records: Vec<TrafficFlow>) -> Self {
+        let mut by_link: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, r) in records.iter().enumerate() {
+            by_link.entry(r.link_id).or_default().push(i);
+        }
+
+        TrafficFlowDataset { records, by_link }
+    }
+
+    /// Decodes the contents of a traffic flow file
+    pub fn from_json(bytes: &[u8]) -> LTAResult<Self> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum File {
+            Wrapped {
+                #[serde(alias = "Value")]
+                value: Vec<TrafficFlow>,
+            },
+            Bare(Vec<TrafficFlow>),
+        }
+
+        match serde_json::from_slice(bytes).map_err(|_| LTAError::FailedToParseBody)? {
+            File::Wrapped { value } | File::Bare(value) => Ok(Self::new(value)),
+        }
+    }
+
+    /// Adds the records of `other`, for datasets that span more than one file
+    pub fn extend(&mut self, other: TrafficFlowDataset) {
+        for r in other.records {
+            self.by_link
+                .entry(r.link_id)
+                .or_default()
+                .push(self.records.len());
+            self.records.push(r);
+        }
+    }
+
+    pub fn records(&self) -> &[TrafficFlow] {
+        &self.records
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the records of a link, across every hour and quarter in the dataset
+    pub fn link(&self, link_id: u64) -> impl Iterator<Item = &TrafficFlow> {
+        self.by_link
+            .get(&link_id)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.records[i])
+    }
+
+    /// Returns the record of a link for a particular hour and quarter
+    pub fn flow(&self, link_id: u64, quarter: Option<Quarter>, hour: u8) -> Option<&TrafficFlow> {
+        self.link(link_id)
+            .find(|r| r.hour == hour && (quarter.is_none() || r.quarter == quarter))
+    }
+
+    /// Returns the quarters in the dataset, earliest first
+    pub fn quarters(&self) -> Vec<Quarter> {
+        let mut quarters = self
+            .records
+            .iter()
+            .filter_map(|r| r.quarter)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        quarters.sort();
+        quarters
+    }
+
+    /// Summarises the flow of each road category, in the order of `RoadCategory`
+    pub fn summary_by_category(&self) -> Vec<CategoryFlow> {
+        let mut summaries: Vec<(CategoryFlow, HashSet<u64>, f64)> = Vec::new();
+
+        for r in &self.records {
+            let idx = match summaries
+                .iter()
+                .position(|(s, _, _)| s.road_category == r.road_category)
+            {
+                Some(idx) => idx,
+                None => {
+                    summaries.push((
+                        CategoryFlow {
+                            road_category: r.road_category.clone(),
+                            links: 0,
+                            records: 0,
+                            mean_volume: 0.0,
+                            max_volume: f64::MIN,
+                        },
+                        HashSet::new(),
+                        0.0,
+                    ));
+                    summaries.len() - 1
+                }
+            };
+
+            let (summary, links, total) = &mut summaries[idx];
+            links.insert(r.link_id);
+            summary.records += 1;
+            summary.max_volume = summary.max_volume.max(r.volume);
+            *total += r.volume;
+        }
+
+        let mut summaries = summaries
+            .into_iter()
+            .map(|(mut summary, links, total)| {
+                summary.links = links.len();
+                summary.mean_volume = total / summary.records as f64;
+                summary
+            })
+            .collect::<Vec<_>>();
+
+        summaries.sort_by_key(|s| category_rank(&s.road_category));
+        summaries
+    }
+}
+
+pub(crate) fn category_rank(category: &RoadCategory) -> u8 {
+    match category {
+        RoadCategory::Expressway => 0,
+        RoadCategory::MajorArterialRoads => 1,
+        RoadCategory::ArterialRoads => 2,
+        RoadCategory::MinorArterialRoads => 3,
+        RoadCategory::SmallRoads => 4,
+        RoadCategory::SlipRoads => 5,
+        RoadCategory::NoCategoryInfoAvail => 6,
+        RoadCategory::Unknown => 7,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLOW: &str = r#"{"Value":[
+        {"LinkID":"103000000","RoadName":"KENT ROAD","RoadCategory":"E","Date":"2023-10-01","HourOfDate":"7","Volume":"120.5"},
+        {"LinkID":"103000000","RoadName":"KENT ROAD","RoadCategory":"E","Date":"2023-10-01","HourOfDate":"8","Volume":"99.5"},
+        {"LinkID":103000010,"RoadName":"PAN ISLAND EXPRESSWAY","RoadCategory":"A","Quarter":"2023Q4","Hour":7,"Volume":2100,
+         "StartLat":"1.3","StartLon":"103.8","EndLat":"1.31","EndLon":"103.81"}
+    ]}"#;
+
+    #[test]
+    fn parse_quarter() {
+        let q = Quarter::new(2023, 4).unwrap();
+        assert_eq!("2023Q4".parse::<Quarter>(), Ok(q));
+        assert_eq!("2023-q4".parse::<Quarter>(), Ok(q));
+        assert_eq!("Q4 2023".parse::<Quarter>(), Ok(q));
+        assert_eq!(q.to_string(), "2023Q4");
+        assert!("2023Q5".parse::<Quarter>().is_err());
+        assert!("Q".parse::<Quarter>().is_err());
+        assert!("Qé2023".parse::<Quarter>().is_err());
+        assert!("Q4é".parse::<Quarter>().is_err());
+        assert_eq!(Quarter::from(time::macros::date!(2023 - 10 - 01)), q);
+    }
+
+    #[test]
+    fn decode_and_lookup() {
+        let dataset = TrafficFlowDataset::from_json(FLOW.as_bytes()).unwrap();
+        assert_eq!(dataset.len(), 3);
+        assert_eq!(dataset.quarters(), vec![Quarter::new(2023, 4).unwrap()]);
+
+        assert_eq!(dataset.link(103000000).count(), 2);
+        assert_eq!(dataset.link(1).count(), 0);
+
+        let flow = dataset.flow(103000000, None, 8).unwrap();
+        assert_eq!(flow.volume, 99.5);
+        assert_eq!(flow.road_category, RoadCategory::SmallRoads);
+        assert!(flow.location.is_none());
+
+        let pie = dataset.link(103000010).next().unwrap();
+        assert_eq!(pie.location.as_ref().unwrap().end.long, 103.81);
+    }
+
+    #[test]
+    fn summary() {
+        let dataset = TrafficFlowDataset::from_json(FLOW.as_bytes()).unwrap();
+        let summary = dataset.summary_by_category();
+
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].road_category, RoadCategory::Expressway);
+        assert_eq!(summary[1].links, 1);
+        assert_eq!(summary[1].records, 2);
+        assert_eq!(summary[1].mean_volume, 110.0);
+        assert_eq!(summary[1].max_volume, 120.5);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let dataset = TrafficFlowDataset::from_json(FLOW.as_bytes()).unwrap();
+        let json = serde_json::to_vec(dataset.records()).unwrap();
+        let decoded = TrafficFlowDataset::from_json(&json).unwrap();
+        assert_eq!(decoded.records(), dataset.records());
+    }
+}
@@ -0,0 +1,347 @@
+//! Short term forecasts of carpark availability from recorded history
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use time::{OffsetDateTime, Weekday};
+
+use super::availability::{least_squares, lot_type_code, AvailabilityHistory, AvailabilitySample};
+use crate::models::traffic::carpark_avail::LotType;
+use crate::utils::{DayType, SGT};
+
+/// Minimum number of past observations of a slot for it to be used
+const MIN_SLOT_SAMPLES: usize = 2;
+
+/// Smallest half width of a band, in lots
+const MIN_SPREAD: f64 = 1.0;
+
+/// Options of [`forecast`] and [`forecast_all`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForecastOptions {
+    /// How far ahead to forecast. Defaults to 15, 30 and 60 minutes
+    pub horizons: Vec<Duration>,
+
+    /// Width of the time of day slots of the seasonal profile. Defaults to 15 minutes
+    pub slot: Duration,
+
+    /// Recent history that the trend is fitted to. Defaults to 30 minutes
+    pub trend_window: Duration,
+
+    /// The trend is weighted by `exp(-horizon / trend_decay)` against the seasonal profile,
+    /// so it dominates short horizons. Defaults to 30 minutes
+    pub trend_decay: Duration,
+
+    /// A recorded value older than this is treated as missing. Defaults to 1 hour
+    pub max_gap: Duration,
+
+    /// Number of standard deviations covered by the bands. Defaults to 1.28, about 80%
+    pub band_z: f64,
+}
+
+impl Default for ForecastOptions {
+    fn default() -> Self {
+        ForecastOptions {
+            horizons: [15, 30, 60]
+                .into_iter()
+                .map(|m| Duration::from_secs(m * 60))
+                .collect(),
+            slot: Duration::from_secs(15 * 60),
+            trend_window: Duration::from_secs(30 * 60),
+            trend_decay: Duration::from_secs(30 * 60),
+            max_gap: Duration::from_secs(60 * 60),
+            band_z: 1.28,
+        }
+    }
+}
+
+/// Forecast of a carpark and lot type at one horizon
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forecast {
+    pub carpark_id: String,
+    pub lot_type: LotType,
+    pub horizon: Duration,
+
+    /// Time that is forecast, in SGT
+    pub at: OffsetDateTime,
+    pub avail_lots: f64,
+    pub lower: f64,
+    pub upper: f64,
+
+    /// `false` if there was too little history for a seasonal profile, so only the trend
+    /// was used
+    pub seasonal: bool,
+}
+
+/// Mean and spread of the values seen in a slot
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SlotStats {
+    pub samples: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+/// Day of the week and time of day profile of a series. Public holidays count as Sundays
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeasonalProfile {
+    slot: Duration,
+    slots: HashMap<(Weekday, u32), SlotStats>,
+}
+
+impl SeasonalProfile {
+    /// Samples the series at the start of every slot, skipping slots whose last recorded value
+    /// is older than `max_gap`
+    pub fn new(series: &[AvailabilitySample], slot: Duration, max_gap: Duration) -> Self {
+        let slot = slot.max(Duration::from_secs(60));
+        let mut sums: HashMap<(Weekday, u32), (usize, f64, f64)> = HashMap::new();
+
+        if let (Some(first), Some(last)) = (series.first(), series.last()) {
+            let step = slot.as_secs() as i64;
+            let mut t = first.at.unix_timestamp().div_euclid(step) * step + step;
+            let mut i = 0;
+
+            while t <= last.at.unix_timestamp() {
+                while i + 1 < series.len() && series[i + 1].at.unix_timestamp() <= t {
+                    i += 1;
+                }
+                let sample = &series[i];
+                let age = (t - sample.at.unix_timestamp()) as u64;
+                if age <= max_gap.as_secs() {
+                    if let Ok(at) = OffsetDateTime::from_unix_timestamp(t) {
+                        let value = sample.avail_lots as f64;
+                        let sum = sums.entry(slot_key(at, slot)).or_default();
+                        *sum = (sum.0 + 1, sum.1 + value, sum.2 + value * value);
+                    }
+                }
+                t += step;
+            }
+        }
+
+        let slots = sums
+            .into_iter()
+            .map(|(key, (n, sum, sum_sq))| {
+                let mean = sum / n as f64;
+                let var = match n {
+                    1 => 0.0,
+                    _ => ((sum_sq - n as f64 * mean * mean) / (n - 1) as f64).max(0.0),
+                };
+                let stats = SlotStats {
+                    samples: n,
+                    mean,
+                    std_dev: var.sqrt(),
+                };
+                (key, stats)
+            })
+            .collect();
+
+        SeasonalProfile { slot, slots }
+    }
+
+    /// Statistics of the slot that `at` falls in
+    pub fn at(&self, at: OffsetDateTime) -> Option<SlotStats> {
+        self.slots.get(&slot_key(at, self.slot)).copied()
+    }
+}
+
+fn slot_key(at: OffsetDateTime, slot: Duration) -> (Weekday, u32) {
+    let at = at.to_offset(SGT);
+    let weekday = match DayType::for_date(at.date()) {
+        DayType::SundayOrHoliday => Weekday::Sunday,
+        _ => at.weekday(),
+    };
+    let secs = at.time().hour() as u64 * 3600 + at.time().minute() as u64 * 60;
+    (weekday, (secs / slot.as_secs()) as u32)
+}
+
+/// Forecasts a carpark and lot type at every horizon of `options`, from the history recorded
+/// up to `now`. `None` if there is no recent value to start from
+pub fn forecast(
+    history: &AvailabilityHistory,
+    carpark_id: &str,
+    lot_type: &LotType,
+    now: OffsetDateTime,
+    options: &ForecastOptions,
+) -> Option<Vec<Forecast>> {
+    let series = history.series(carpark_id, lot_type);
+    let series = &series[..series.partition_point(|s| s.at <= now)];
+
+    let current = series.last()?;
+    if (now - current.at) > options.max_gap {
+        return None;
+    }
+    let level = current.avail_lots as f64;
+    let capacity = series.iter().map(|s| s.avail_lots).max()? as f64;
+
+    let (slope, spread_trend) = fit_trend(series, now, options.trend_window);
+    let profile = SeasonalProfile::new(series, options.slot, options.max_gap);
+    let profile_now = profile.at(now).filter(|s| s.samples >= MIN_SLOT_SAMPLES);
+    let window_hours = options.trend_window.as_secs_f64().max(60.0) / 3600.0;
+    let decay_hours = options.trend_decay.as_secs_f64().max(1.0) / 3600.0;
+
+    let forecasts = options
+        .horizons
+        .iter()
+        .map(|&horizon| {
+            let hours = horizon.as_secs_f64() / 3600.0;
+            let at = (now + horizon).to_offset(SGT);
+
+            let by_trend = level + slope * hours;
+            let sigma_trend = spread_trend * (1.0 + hours / window_hours).sqrt();
+
+            let seasonal =
+                profile_now.zip(profile.at(at).filter(|s| s.samples >= MIN_SLOT_SAMPLES));
+            let (estimate, sigma) = match seasonal {
+                Some((from, to)) => {
+                    let w = (-hours / decay_hours).exp();
+                    let by_profile = level + to.mean - from.mean;
+                    (
+                        w * by_trend + (1.0 - w) * by_profile,
+                        w * sigma_trend + (1.0 - w) * to.std_dev,
+                    )
+                }
+                None => (by_trend, sigma_trend),
+            };
+
+            let estimate = estimate.clamp(0.0, capacity);
+            let spread = (options.band_z * sigma).max(MIN_SPREAD);
+            Forecast {
+                carpark_id: carpark_id.to_string(),
+                lot_type: lot_type.clone(),
+                horizon,
+                at,
+                avail_lots: estimate,
+                lower: (estimate - spread).max(0.0),
+                upper: (estimate + spread).min(capacity),
+                seasonal: seasonal.is_some(),
+            }
+        })
+        .collect();
+
+    Some(forecasts)
+}
+
+/// Forecasts every carpark and lot type of `history` that has a recent value
+pub fn forecast_all(
+    history: &AvailabilityHistory,
+    now: OffsetDateTime,
+    options: &ForecastOptions,
+) -> Vec<Forecast> {
+    let mut forecasts = history
+        .series_keys()
+        .filter_map(|(carpark_id, lot_type)| forecast(history, carpark_id, &lot_type, now, options))
+        .flatten()
+        .collect::<Vec<_>>();
+
+    forecasts.sort_by(|a, b| {
+        a.carpark_id
+            .cmp(&b.carpark_id)
+            .then(lot_type_code(&a.lot_type).cmp(lot_type_code(&b.lot_type)))
+            .then(a.horizon.cmp(&b.horizon))
+    });
+    forecasts
+}
+
+/// Slope in lots per hour and root mean square error of the line fitted to the values of the
+/// trend window. The value held at the start of the window is carried into it
+fn fit_trend(series: &[AvailabilitySample], now: OffsetDateTime, window: Duration) -> (f64, f64) {
+    let from = now - window;
+    let start = series.partition_point(|s| s.at <= from);
+    let carried = start.checked_sub(1).map(|i| AvailabilitySample {
+        at: from,
+        ..series[i]
+    });
+
+    let points = carried
+        .iter()
+        .chain(&series[start..])
+        .map(|s| ((s.at - from).as_seconds_f64() / 3600.0, s.avail_lots as f64))
+        .collect::<Vec<_>>();
+
+    match least_squares(&points) {
+        Some((slope, intercept)) => {
+            let sq = points
+                .iter()
+                .map(|(x, y)| (y - (slope * x + intercept)).powi(2))
+                .sum::<f64>();
+            (slope, (sq / points.len() as f64).sqrt())
+        }
+        None => (0.0, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    /// Weekdays fill up from 0800 to 1000 and empty at 1800, weekends stay at 180 lots
+    fn lots_at(at: OffsetDateTime) -> u32 {
+        let at = at.to_offset(SGT);
+        if matches!(at.weekday(), Weekday::Saturday | Weekday::Sunday) {
+            return 180;
+        }
+
+        let hours = at.hour() as f64 + at.minute() as f64 / 60.0;
+        match hours {
+            h if !(8.0..18.0).contains(&h) => 200,
+            h if h < 10.0 => (200.0 - 75.0 * (h - 8.0)) as u32,
+            _ => 50,
+        }
+    }
+
+    /// Recorded every 5 minutes from Monday 2024-03-04 until `until`, in the format of
+    /// `AvailabilityRecorder`
+    fn fixture(until: OffsetDateTime) -> AvailabilityHistory {
+        let start = datetime!(2024-03-04 00:00 +8);
+        let csv = (0..)
+            .map(|i| start + time::Duration::minutes(5 * i))
+            .take_while(|at| *at <= until)
+            .map(|at| format!("{},A,C,{}\n", at.unix_timestamp(), lots_at(at)))
+            .collect::<String>();
+        AvailabilityHistory::from_csv(&csv)
+    }
+
+    #[test]
+    fn blends_profile_and_trend() {
+        let now = datetime!(2024-03-25 08:00 +8);
+        let history = fixture(now);
+        let forecasts = forecast(&history, "A", &LotType::C, now, &Default::default()).unwrap();
+
+        assert_eq!(forecasts.len(), 3);
+        assert!(forecasts.iter().all(|f| f.seasonal));
+        assert_eq!(forecasts[2].at, datetime!(2024-03-25 09:00 +8));
+
+        // The flat trend holds the short horizons near 200 while the profile pulls the longer
+        // ones towards the morning fill
+        let lots = forecasts.iter().map(|f| f.avail_lots).collect::<Vec<_>>();
+        assert!(lots.windows(2).all(|w| w[0] > w[1]), "{:?}", lots);
+        assert!(lots[0] < 200.0 && lots[2] > 125.0 && lots[2] < 150.0);
+        assert!(forecasts
+            .iter()
+            .all(|f| f.lower < f.avail_lots && f.avail_lots < f.upper && f.upper <= 200.0));
+
+        // Weekends follow their own profile
+        let now = datetime!(2024-03-23 08:00 +8);
+        let forecasts = forecast(&fixture(now), "A", &LotType::C, now, &Default::default());
+        assert!(forecasts
+            .unwrap()
+            .iter()
+            .all(|f| (f.avail_lots - 180.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn trend_only_and_missing() {
+        // Nine hours of Monday morning is too little history for a profile of any day
+        let now = datetime!(2024-03-04 09:00 +8);
+        let history = fixture(now);
+        let forecasts = forecast_all(&history, now, &Default::default());
+
+        assert_eq!(forecasts.len(), 3);
+        assert!(forecasts.iter().all(|f| !f.seasonal));
+        assert!((forecasts[0].avail_lots - (125.0 - 75.0 / 4.0)).abs() < 2.0);
+        assert!((forecasts[2].avail_lots - 50.0).abs() < 2.0);
+
+        assert!(forecast(&history, "B", &LotType::C, now, &Default::default()).is_none());
+        let stale = now + time::Duration::hours(2);
+        assert!(forecast(&history, "A", &LotType::C, stale, &Default::default()).is_none());
+    }
+}
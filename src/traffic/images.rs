@@ -0,0 +1,332 @@
+//! Traffic camera images and an archive that stores them by date
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+use crate::models::traffic::prelude::TrafficImage;
+use crate::models::utils::Coordinates;
+use crate::utils::SGT;
+use crate::LTAResult;
+
+/// Default number of images that are downloaded at the same time
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// JPEG of a traffic camera
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraImage {
+    pub camera_id: u32,
+    pub location: Coordinates,
+
+    /// Read from the name of the image when present, otherwise the time the link was signed,
+    /// otherwise the time the image was downloaded
+    pub captured_at: OffsetDateTime,
+    pub bytes: Vec<u8>,
+}
+
+impl CameraImage {
+    pub fn new(image: &TrafficImage, bytes: Vec<u8>) -> Self {
+        CameraImage {
+            camera_id: image.camera_id,
+            location: Coordinates::new(image.lat, image.long),
+            captured_at: capture_time(&image.image_link)
+                .unwrap_or_else(|| OffsetDateTime::now_utc().to_offset(SGT)),
+            bytes,
+        }
+    }
+
+    /// Hash of the JPEG, stable across runs and platforms
+    pub fn content_hash(&self) -> u64 {
+        content_hash(&self.bytes)
+    }
+}
+
+/// Returns the images of `camera_ids`, or all of them if `camera_ids` is empty
+#[cfg(any(feature = "blocking-traits", feature = "non-blocking-traits"))]
+pub(crate) fn select_cameras(images: Vec<TrafficImage>, camera_ids: &[u32]) -> Vec<TrafficImage> {
+    if camera_ids.is_empty() {
+        return images;
+    }
+
+    images
+        .into_iter()
+        .filter(|i| camera_ids.contains(&i.camera_id))
+        .collect()
+}
+
+/// Image links look like `.../2024-03-01/08-30/1001_0829_20240301083012_a1b2c3.jpg?X-Amz-...`,
+/// where the 14 digit part is the capture time in SGT
+fn capture_time(link: &str) -> Option<OffsetDateTime> {
+    let (path, query) = link.split_once('?').unwrap_or((link, ""));
+    let name = path.rsplit('/').next()?;
+    let stamp_fmt = format_description!("[year][month][day][hour][minute][second]");
+
+    let from_name = name
+        .split(['_', '.'])
+        .filter(|part| part.len() == 14 && part.bytes().all(|b| b.is_ascii_digit()))
+        .find_map(|part| PrimitiveDateTime::parse(part, stamp_fmt).ok())
+        .map(|d| d.assume_offset(SGT));
+
+    let amz_fmt = format_description!("[year][month][day]T[hour][minute][second]Z");
+    from_name.or_else(|| {
+        query
+            .split('&')
+            .filter_map(|kv| kv.split_once('='))
+            .find(|(k, _)| *k == "X-Amz-Date")
+            .and_then(|(_, v)| PrimitiveDateTime::parse(v, amz_fmt).ok())
+            .map(|d| d.assume_utc().to_offset(SGT))
+    })
+}
+
+/// 64 bit FNV-1a
+fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Outcome of archiving one round of images
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchiveReport {
+    /// Files that were written
+    pub written: Vec<PathBuf>,
+
+    /// Cameras whose image has not changed since the last one archived
+    pub unchanged: Vec<u32>,
+
+    /// Cameras whose image could not be downloaded
+    pub failed: Vec<u32>,
+
+    /// Cameras whose image could not be written, with the reason
+    pub unwritten: Vec<(u32, String)>,
+}
+
+/// Stores camera images as `<root>/<YYYY-MM-DD>/<camera_id>/<HHMMSS>_<hash>.jpg`, using the
+/// capture date and time in SGT. An image is skipped when it has the same content hash as the
+/// last one stored for its camera, including ones stored by earlier runs
+#[derive(Debug, Clone)]
+pub struct ImageArchiver {
+    root: PathBuf,
+    camera_ids: Vec<u32>,
+    concurrency: usize,
+    last_hash: HashMap<u32, Option<u64>>,
+}
+
+impl ImageArchiver {
+    /// Archives every camera into `root`, downloading [`DEFAULT_CONCURRENCY`] images at a time
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        ImageArchiver {
+            root: root.into(),
+            camera_ids: Vec::new(),
+            concurrency: DEFAULT_CONCURRENCY,
+            last_hash: HashMap::new(),
+        }
+    }
+
+    /// Only archives `camera_ids`
+    pub fn cameras(mut self, camera_ids: impl Into<Vec<u32>>) -> Self {
+        self.camera_ids = camera_ids.into();
+        self
+    }
+
+    /// Maximum number of images downloaded at the same time, at least 1
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn camera_ids(&self) -> &[u32] {
+        &self.camera_ids
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Path that `image` is stored at
+    pub fn path_for(&self, image: &CameraImage) -> PathBuf {
+        let captured_at = image.captured_at.to_offset(SGT);
+        let day = captured_at
+            .format(format_description!("[year]-[month]-[day]"))
+            .unwrap_or_default();
+        let time = captured_at
+            .format(format_description!("[hour][minute][second]"))
+            .unwrap_or_default();
+
+        self.root
+            .join(day)
+            .join(image.camera_id.to_string())
+            .join(format!("{}_{:016x}.jpg", time, image.content_hash()))
+    }
+
+    /// Writes the images that have changed since the last round. A camera whose image cannot be
+    /// written does not stop the others from being stored
+    pub fn store(
+        &mut self,
+        images: impl IntoIterator<Item = (u32, LTAResult<CameraImage>)>,
+    ) -> ArchiveReport {
+        let mut report = ArchiveReport::default();
+
+        for (camera_id, image) in images {
+            let image = match image {
+                Ok(image) => image,
+                Err(_) => {
+                    report.failed.push(camera_id);
+                    continue;
+                }
+            };
+
+            let hash = image.content_hash();
+            if self.last_hash_of(image.camera_id) == Some(hash) {
+                report.unchanged.push(image.camera_id);
+                continue;
+            }
+
+            let path = self.path_for(&image);
+            let written = match path.parent() {
+                Some(dir) => fs::create_dir_all(dir),
+                None => Ok(()),
+            }
+            .and_then(|_| fs::write(&path, &image.bytes));
+
+            match written {
+                Ok(()) => {
+                    self.last_hash.insert(image.camera_id, Some(hash));
+                    report.written.push(path);
+                }
+                Err(e) => report.unwritten.push((image.camera_id, e.to_string())),
+            }
+        }
+
+        report
+    }
+
+    /// Looks up the hash of the latest stored image of a camera, reading it from the archive
+    /// the first time the camera is seen
+    fn last_hash_of(&mut self, camera_id: u32) -> Option<u64> {
+        if let Some(hash) = self.last_hash.get(&camera_id) {
+            return *hash;
+        }
+
+        let hash = latest_stored_hash(&self.root, camera_id);
+        self.last_hash.insert(camera_id, hash);
+        hash
+    }
+}
+
+fn latest_stored_hash(root: &Path, camera_id: u32) -> Option<u64> {
+    let mut days = fs::read_dir(root)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect::<Vec<_>>();
+    days.sort();
+
+    days.iter().rev().find_map(|day| {
+        let latest = fs::read_dir(day.join(camera_id.to_string()))
+            .ok()?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|name| name.ends_with(".jpg"))
+            .max()?;
+
+        let (_, hash) = latest.trim_end_matches(".jpg").split_once('_')?;
+        u64::from_str_radix(hash, 16).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LTAError;
+    use time::macros::datetime;
+
+    fn traffic_image(camera_id: u32, link: &str) -> TrafficImage {
+        TrafficImage {
+            camera_id,
+            lat: 1.3,
+            long: 103.8,
+            image_link: link.to_string(),
+        }
+    }
+
+    #[test]
+    fn capture_time_from_link() {
+        let from_name = capture_time(
+            "https://dm-traffic-camera-itsc.s3.amazonaws.com/2024-03-01/08-30/1001_0829_20240301083012_a1b2c3.jpg?X-Amz-Date=20240301T003100Z",
+        );
+        assert_eq!(from_name, Some(datetime!(2024-03-01 08:30:12 +8)));
+
+        let from_query = capture_time("https://example.com/1001.jpg?X-Amz-Date=20240301T003100Z");
+        assert_eq!(from_query, Some(datetime!(2024-03-01 08:31:00 +8)));
+
+        assert_eq!(capture_time("https://example.com/1001.jpg"), None);
+    }
+
+    #[test]
+    fn archive_skips_unchanged() {
+        let root = std::env::temp_dir().join(format!("lta-archive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let first = traffic_image(1001, "https://example.com/1001_0829_20240301083012_a.jpg");
+        let second = traffic_image(1001, "https://example.com/1001_0834_20240301083512_b.jpg");
+
+        let mut archiver = ImageArchiver::new(&root);
+        let report = archiver.store(vec![
+            (1001, Ok(CameraImage::new(&first, b"jpeg 1".to_vec()))),
+            (4703, Err(LTAError::NotFound)),
+        ]);
+        assert_eq!(report.written.len(), 1);
+        assert_eq!(report.failed, vec![4703]);
+        assert!(report.written[0].starts_with(root.join("2024-03-01").join("1001")));
+
+        let report = archiver.store(vec![(
+            1001,
+            Ok(CameraImage::new(&second, b"jpeg 1".to_vec())),
+        )]);
+        assert_eq!(report.unchanged, vec![1001]);
+
+        // A new archiver picks up the hash of the last stored image
+        let mut archiver = ImageArchiver::new(&root);
+        let report = archiver.store(vec![(
+            1001,
+            Ok(CameraImage::new(&second, b"jpeg 1".to_vec())),
+        )]);
+        assert_eq!(report.unchanged, vec![1001]);
+
+        let report = archiver.store(vec![(
+            1001,
+            Ok(CameraImage::new(&second, b"jpeg 2".to_vec())),
+        )]);
+        assert_eq!(report.written.len(), 1);
+
+        // A camera that cannot be written is reported without losing the others
+        let blocked = traffic_image(4703, "https://example.com/4703_0829_20240301083012_c.jpg");
+        fs::write(root.join("2024-03-01").join("4703"), b"not a directory").unwrap();
+        let report = archiver.store(vec![
+            (4703, Ok(CameraImage::new(&blocked, b"jpeg 3".to_vec()))),
+            (1001, Ok(CameraImage::new(&second, b"jpeg 3".to_vec()))),
+        ]);
+        assert_eq!(report.written.len(), 1);
+        assert_eq!(report.unwritten.len(), 1);
+        assert_eq!(report.unwritten[0].0, 4703);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking-traits", feature = "non-blocking-traits"))]
+    fn select_all_or_some() {
+        let images = vec![traffic_image(1001, ""), traffic_image(4703, "")];
+        assert_eq!(select_cameras(images.clone(), &[]).len(), 2);
+        assert_eq!(select_cameras(images, &[4703])[0].camera_id, 4703);
+    }
+}
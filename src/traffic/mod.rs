@@ -0,0 +1,26 @@
+pub mod availability;
+pub mod carpark;
+pub mod congestion;
+pub mod erp;
+pub mod flow;
+pub mod forecast;
+pub mod images;
+pub mod travel_time;
+
+pub use availability::{
+    lot_type_code, lot_type_from_code, AvailabilityHistory, AvailabilityRecorder,
+    AvailabilitySample, AvailabilityTrend, RecordReport, CARPARK_PAGE_SIZE, DEFAULT_HEARTBEAT,
+};
+pub use carpark::{
+    carpark_location, is_valid_location, parse_location, CarParkFinder, CarParkMatch, CarParkQuery,
+    SortBy,
+};
+pub use congestion::{
+    link_congestion, CongestionIndex, CongestionSnapshot, CongestionTracker, IndexPoint, SpeedDrop,
+    DEFAULT_SHARP_DROP,
+};
+pub use erp::{erp_day_type, in_band, CrossingCharge, ErpCalculator, TripCharge, ZoneCrossing};
+pub use flow::{CategoryFlow, Quarter, TrafficFlow, TrafficFlowDataset};
+pub use forecast::{forecast, forecast_all, Forecast, ForecastOptions, SeasonalProfile, SlotStats};
+pub use images::{ArchiveReport, CameraImage, ImageArchiver};
+pub use travel_time::{ChainGap, ExpresswayJourney, ExpresswayTimes, SegmentTime};
@@ -0,0 +1,250 @@
+//! Journey times along an expressway, chained from the segments of `get_est_travel_time`
+
+use crate::models::traffic::prelude::{EstTravelTime, Highway, HighwayDirection};
+
+/// Estimated time of one segment of a journey
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentTime {
+    pub start_pt: String,
+    pub end_pt: String,
+
+    /// In minutes
+    pub est_travel_time: u32,
+}
+
+/// Segments between two points of an expressway, in the order they are driven
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpresswayJourney {
+    pub name: Highway,
+    pub direction: HighwayDirection,
+    pub segments: Vec<SegmentTime>,
+
+    /// In minutes
+    pub total: u32,
+}
+
+/// A point where the segments of an expressway stop before reaching its far end point
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainGap {
+    pub name: Highway,
+    pub direction: HighwayDirection,
+
+    /// No segment starts from this point
+    pub broken_at: String,
+    pub far_end_pt: String,
+}
+
+/// Points are compared ignoring case and surrounding whitespace
+fn same_point(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+/// Journey time lookups over the segments of every expressway. Build it from the full result
+/// of `get_est_travel_time`
+#[derive(Debug, Clone, Default)]
+pub struct ExpresswayTimes {
+    segments: Vec<EstTravelTime>,
+}
+
+impl ExpresswayTimes {
+    pub fn new(segments: Vec<EstTravelTime>) -> Self {
+        ExpresswayTimes { segments }
+    }
+
+    /// Every expressway and direction, in the order they first appear
+    pub fn expressways(&self) -> Vec<(&Highway, &HighwayDirection)> {
+        let mut found: Vec<(&Highway, &HighwayDirection)> = Vec::new();
+        for s in &self.segments {
+            if !found.contains(&(&s.name, &s.direction)) {
+                found.push((&s.name, &s.direction));
+            }
+        }
+        found
+    }
+
+    fn segments_of(&self, name: &Highway, direction: &HighwayDirection) -> Vec<&EstTravelTime> {
+        self.segments
+            .iter()
+            .filter(|s| s.name == *name && s.direction == *direction)
+            .collect()
+    }
+
+    fn next_segment(
+        &self,
+        name: &Highway,
+        direction: &HighwayDirection,
+        from: &str,
+    ) -> Option<&EstTravelTime> {
+        self.segments_of(name, direction)
+            .into_iter()
+            .find(|s| same_point(&s.start_pt, from))
+    }
+
+    /// Chains of consecutive segments, each starting from a point that no segment ends at
+    fn chains(&self, name: &Highway, direction: &HighwayDirection) -> Vec<Vec<&EstTravelTime>> {
+        let segments = self.segments_of(name, direction);
+        let heads = segments
+            .iter()
+            .filter(|s| !segments.iter().any(|o| same_point(&o.end_pt, &s.start_pt)));
+
+        heads
+            .map(|head| {
+                let mut chain = vec![*head];
+                while let Some(next) =
+                    self.next_segment(name, direction, &chain[chain.len() - 1].end_pt)
+                {
+                    // Guards against segments that loop back
+                    if chain.iter().any(|s| std::ptr::eq(*s, next)) {
+                        break;
+                    }
+                    chain.push(next);
+                }
+                chain
+            })
+            .collect()
+    }
+
+    /// Points that journeys of an expressway can start or end at, in the order they are driven
+    pub fn points(&self, name: &Highway, direction: &HighwayDirection) -> Vec<&str> {
+        let mut points = Vec::new();
+        for chain in self.chains(name, direction) {
+            points.extend(chain.iter().map(|s| s.start_pt.as_str()));
+            points.extend(chain.last().map(|s| s.end_pt.as_str()));
+        }
+        points
+    }
+
+    /// Chains segments from `from` to `to`. `None` if either point is not on the expressway,
+    /// `to` is not ahead of `from`, or the chain breaks between them
+    pub fn journey(
+        &self,
+        name: &Highway,
+        direction: &HighwayDirection,
+        from: &str,
+        to: &str,
+    ) -> Option<ExpresswayJourney> {
+        let mut segments = Vec::new();
+        let mut at = from;
+
+        while !same_point(at, to) {
+            let segment = self.next_segment(name, direction, at)?;
+            if segments
+                .iter()
+                .any(|s: &SegmentTime| same_point(&s.start_pt, &segment.start_pt))
+            {
+                return None;
+            }
+
+            segments.push(SegmentTime {
+                start_pt: segment.start_pt.clone(),
+                end_pt: segment.end_pt.clone(),
+                est_travel_time: segment.est_travel_time,
+            });
+            at = &segment.end_pt;
+        }
+
+        if segments.is_empty() {
+            return None;
+        }
+        Some(ExpresswayJourney {
+            name: name.clone(),
+            direction: direction.clone(),
+            total: segments.iter().map(|s| s.est_travel_time).sum(),
+            segments,
+        })
+    }
+
+    /// Points where the segments of an expressway stop before reaching its far end point
+    pub fn gaps(&self, name: &Highway, direction: &HighwayDirection) -> Vec<ChainGap> {
+        self.chains(name, direction)
+            .into_iter()
+            .filter_map(|chain| {
+                let last = chain.last()?;
+                (!same_point(&last.end_pt, &last.far_end_pt)).then(|| ChainGap {
+                    name: name.clone(),
+                    direction: direction.clone(),
+                    broken_at: last.end_pt.clone(),
+                    far_end_pt: last.far_end_pt.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(name: Highway, far_end_pt: &str, legs: &[(&str, &str, u32)]) -> Vec<EstTravelTime> {
+        legs.iter()
+            .map(|&(start_pt, end_pt, est_travel_time)| EstTravelTime {
+                name: name.clone(),
+                direction: HighwayDirection::EastToWest,
+                far_end_pt: far_end_pt.to_string(),
+                start_pt: start_pt.to_string(),
+                end_pt: end_pt.to_string(),
+                est_travel_time,
+            })
+            .collect()
+    }
+
+    fn times() -> ExpresswayTimes {
+        let mut rows = segments(
+            Highway::PIE,
+            "TUAS CHECKPOINT",
+            &[
+                ("ADAM RD", "BKE", 4),
+                ("CHANGI", "ADAM RD", 12),
+                ("BKE", "TUAS CHECKPOINT", 9),
+            ],
+        );
+        rows.extend(segments(
+            Highway::AYE,
+            "TUAS CHECKPOINT",
+            &[("MARINA", "CLEMENTI", 8), ("JURONG", "TUAS CHECKPOINT", 6)],
+        ));
+        ExpresswayTimes::new(rows)
+    }
+
+    #[test]
+    fn chained_journey() {
+        let times = times();
+        let pie = (Highway::PIE, HighwayDirection::EastToWest);
+
+        assert_eq!(
+            times.points(&pie.0, &pie.1),
+            ["CHANGI", "ADAM RD", "BKE", "TUAS CHECKPOINT"]
+        );
+
+        let journey = times.journey(&pie.0, &pie.1, "changi", "BKE").unwrap();
+        assert_eq!(journey.total, 16);
+        assert_eq!(journey.segments.len(), 2);
+        assert_eq!(journey.segments[1].start_pt, "ADAM RD");
+
+        assert!(times.journey(&pie.0, &pie.1, "BKE", "CHANGI").is_none());
+        assert!(times.journey(&pie.0, &pie.1, "BKE", "BKE").is_none());
+        assert!(times
+            .journey(&pie.0, &HighwayDirection::WestToEast, "CHANGI", "BKE")
+            .is_none());
+        assert!(times.gaps(&pie.0, &pie.1).is_empty());
+    }
+
+    #[test]
+    fn broken_chain() {
+        let times = times();
+        let aye = (Highway::AYE, HighwayDirection::EastToWest);
+
+        assert_eq!(times.expressways().len(), 2);
+        assert!(times
+            .journey(&aye.0, &aye.1, "MARINA", "TUAS CHECKPOINT")
+            .is_none());
+
+        let gaps = times.gaps(&aye.0, &aye.1);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].broken_at, "CLEMENTI");
+        assert_eq!(
+            times.points(&aye.0, &aye.1),
+            ["MARINA", "CLEMENTI", "JURONG", "TUAS CHECKPOINT"]
+        );
+    }
+}
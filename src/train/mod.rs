@@ -0,0 +1,69 @@
+//! Station listings for the train APIs
+
+use serde::de::value::{Error as DeError, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+
+use crate::models::train::prelude::*;
+
+/// Highest station number used by any line
+const MAX_STATION_NO: u8 = 40;
+
+/// Returns the station codes on `line`, in order. Interchange codes that are shared by two
+/// lines, such as `PTC` and `STC`, are included in both
+pub fn station_codes(line: &MrtLine) -> Vec<StationCode> {
+    let (prefixes, terminals): (&[&str], &[&str]) = match line {
+        MrtLine::CCL => (&["CC"], &[]),
+        MrtLine::CEL => (&["CE"], &[]),
+        MrtLine::CGL => (&["CG"], &["CG"]),
+        MrtLine::DTL => (&["DT"], &[]),
+        MrtLine::EWL => (&["EW"], &[]),
+        MrtLine::NEL => (&["NE"], &[]),
+        MrtLine::NSL => (&["NS"], &[]),
+        MrtLine::PEL => (&["PE"], &["PTC"]),
+        MrtLine::PWL => (&["PW"], &["PTC"]),
+        MrtLine::SEL => (&["SE"], &["STC"]),
+        MrtLine::SWL => (&["SW"], &["STC"]),
+        MrtLine::BPL => (&["BP"], &[]),
+        MrtLine::Unknown => (&[], &[]),
+    };
+
+    let numbered = prefixes
+        .iter()
+        .flat_map(|p| (1..=MAX_STATION_NO).map(move |n| format!("{}{}", p, n)));
+
+    terminals
+        .iter()
+        .map(|t| t.to_string())
+        .chain(numbered)
+        .filter_map(|code| parse_station_code(&code))
+        .collect()
+}
+
+/// Returns `None` for codes that `StationCode` does not know about
+fn parse_station_code(code: &str) -> Option<StationCode> {
+    let de: StrDeserializer<DeError> = code.into_deserializer();
+    match StationCode::deserialize(de) {
+        Ok(StationCode::Unknown) | Err(_) => None,
+        Ok(code) => Some(code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stations_on_line() {
+        let nsl = station_codes(&MrtLine::NSL);
+        assert_eq!(nsl.len(), 28);
+        assert_eq!(nsl[0], StationCode::NS1);
+        assert_eq!(nsl[27], StationCode::NS28);
+
+        let pwl = station_codes(&MrtLine::PWL);
+        assert_eq!(pwl[0], StationCode::PTC);
+        assert_eq!(pwl[1], StationCode::PW1);
+
+        assert!(station_codes(&MrtLine::Unknown).is_empty());
+    }
+}
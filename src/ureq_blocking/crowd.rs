@@ -6,9 +6,10 @@ use lta_models::{
         StationCrowdLevelRawResp, VolType,
     },
 };
-use time::{macros::format_description, Date};
 use ureq::Agent;
 
+use crate::crowd::passenger_vol::{query_month, YearMonth};
+use crate::link::{LinkSource, PresignedLink};
 use crate::{
     blocking::{prelude::CrowdRequests, ClientExt, LTAClient},
     vol_type_to_url, Client, Crowd, LTAResult,
@@ -18,26 +19,29 @@ impl CrowdRequests<LTAClient<Agent>> for Crowd {
     fn get_passenger_vol_by(
         client: &LTAClient<Agent>,
         vol_type: VolType,
-        date: impl Into<Option<Date>>,
+        date: impl Into<Option<YearMonth>>,
         skip: impl Into<Option<u32>>,
-    ) -> LTAResult<Vec<String>> {
-        let format = format_description!("[year]-[month]-[day]");
-        let fmt_date = date
-            .into()
-            .map(|f| f.format(&format))
-            .expect("Failed to format.")
-            .ok();
+    ) -> LTAResult<Vec<PresignedLink>> {
+        let (month, skip) = (date.into(), skip.into());
+        let fmt_date = query_month(month)?;
+        let url = vol_type_to_url(client.base_url(), vol_type.clone())?;
 
-        let url = vol_type_to_url(client.base_url(), vol_type)?;
-
-        match fmt_date {
+        let urls: Vec<String> = match fmt_date {
             Some(nd) => client
                 .build_req_with_query::<passenger_vol::PassengerVolRawResp, _, _>(&url, |rb| {
                     rb.query("Date", &nd)
-                }),
-            None => client
-                .build_req_with_skip::<passenger_vol::PassengerVolRawResp, _>(&url, skip.into()),
-        }
+                })?,
+            None => {
+                client.build_req_with_skip::<passenger_vol::PassengerVolRawResp, _>(&url, skip)?
+            }
+        };
+
+        let source = LinkSource::PassengerVol {
+            vol_type,
+            month,
+            skip,
+        };
+        Ok(PresignedLink::from_urls(urls, source))
     }
 
     fn get_crowd_density_rt(
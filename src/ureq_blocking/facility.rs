@@ -1,6 +1,7 @@
 use lta_models::{facility::prelude::FacilityMaintenanceRawResp, prelude::StationCode};
 use ureq::Agent;
 
+use crate::link::{LinkSource, PresignedLink};
 use crate::{
     blocking::{prelude::FacilityRequests, ClientExt, LTAClient},
     Client, Facility, LTAResult,
@@ -12,10 +13,14 @@ impl FacilityRequests<LTAClient<Agent>> for Facility {
     fn get_facilities_maintenance(
         client: &LTAClient<Agent>,
         station_code: StationCode,
-    ) -> LTAResult<Vec<String>> {
-        client.build_req_with_query::<FacilityMaintenanceRawResp, _, _>(
-            &concat_string!(client.base_url(), "/FacilitiesMaintenance"),
-            |rb| rb.query("StationCode", &format!("{:?}", station_code)),
-        )
+    ) -> LTAResult<Vec<PresignedLink>> {
+        client
+            .build_req_with_query::<FacilityMaintenanceRawResp, _, _>(
+                &concat_string!(client.base_url(), "/FacilitiesMaintenance"),
+                |rb| rb.query("StationCode", &format!("{:?}", station_code)),
+            )
+            .map(|urls| {
+                PresignedLink::from_urls(urls, LinkSource::FacilitiesMaintenance(station_code))
+            })
     }
 }
@@ -1,6 +1,7 @@
 use lta_models::geo::prelude::{GeospatialLayerId, GeospatialWholeIslandRawResp};
 use ureq::Agent;
 
+use crate::link::{LinkSource, PresignedLink};
 use crate::{
     blocking::{prelude::GeoRequests, ClientExt, LTAClient},
     Client, Geo, LTAResult,
@@ -11,10 +12,12 @@ impl GeoRequests<LTAClient<Agent>> for Geo {
     fn get_geospatial_whole_island(
         client: &LTAClient<Agent>,
         id: GeospatialLayerId,
-    ) -> LTAResult<Vec<String>> {
-        client.build_req_with_query::<GeospatialWholeIslandRawResp, _, _>(
-            &concat_string!(client.base_url(), "/GeospatialWholeIsland"),
-            |rb| rb.query("ID", &format!("{:?}", id)),
-        )
+    ) -> LTAResult<Vec<PresignedLink>> {
+        client
+            .build_req_with_query::<GeospatialWholeIslandRawResp, _, _>(
+                &concat_string!(client.base_url(), "/GeospatialWholeIsland"),
+                |rb| rb.query("ID", &format!("{:?}", id)),
+            )
+            .map(|urls| PresignedLink::from_urls(urls, LinkSource::GeospatialWholeIsland(id)))
     }
 }
@@ -11,6 +11,7 @@ use crate::{
     blocking::{ClientExt, LTAClient},
     Client, LTAError, LTAResult,
 };
+use std::io::Read;
 pub use ureq::Agent;
 use ureq::Response;
 
@@ -25,8 +26,7 @@ impl ClientExt for LTAClient<Agent> {
             .query("$skip", skip.to_string().as_str());
 
         rb.call()
-            .map_err(|e| LTAError::BackendError(Box::new(e)))
-            .and_then(handle_status_code)?
+            .map_or_else(handle_call_error, handle_status_code)?
             .into_json::<T>()
             .map(Into::into)
             .map_err(|_| LTAError::FailedToParseBody)
@@ -40,12 +40,34 @@ impl ClientExt for LTAClient<Agent> {
         let rb = self.req_builder(url);
         query(rb)
             .call()
-            .map_err(|e| LTAError::BackendError(Box::new(e)))
-            .and_then(handle_status_code)?
+            .map_or_else(handle_call_error, handle_status_code)?
             .into_json::<T>()
             .map(Into::into)
             .map_err(|_| LTAError::FailedToParseBody)
     }
+
+    fn download(&self, url: &str) -> LTAResult<Vec<u8>> {
+        let mut buf = Vec::new();
+
+        self.client
+            .get(url)
+            .call()
+            .map_or_else(handle_call_error, handle_status_code)?
+            .into_reader()
+            .read_to_end(&mut buf)
+            .map_err(|_| LTAError::FailedToParseBody)?;
+
+        Ok(buf)
+    }
+}
+
+/// ureq returns 4xx and 5xx responses as errors, those go through `handle_status_code` too so
+/// that they map to the same `LTAError` as the other backends
+fn handle_call_error(err: ureq::Error) -> LTAResult<Response> {
+    match err {
+        ureq::Error::Status(_, res) => handle_status_code(res),
+        e => Err(LTAError::BackendError(Box::new(e))),
+    }
 }
 
 fn handle_status_code(res: Response) -> LTAResult<Response> {
@@ -97,10 +119,19 @@ mod tests {
 
     fn get_client() -> LTAClient<Agent> {
         let api_key = env::var("API_KEY").expect("API_KEY does not exist!");
-        let client =
-            LTAClient::with_api_key(api_key, "http://datamall2.mytransport.sg/ltaodataservice")
-                .unwrap();
-        client
+        LTAClient::with_api_key(api_key, "http://datamall2.mytransport.sg/ltaodataservice").unwrap()
+    }
+
+    #[test]
+    fn forbidden_status_code() {
+        let res = ureq::Response::new(403, "Forbidden", "Request has expired").unwrap();
+        match super::handle_call_error(ureq::Error::Status(403, res)) {
+            Err(LTAError::UnhandledStatusCode(code, body)) => {
+                assert_eq!(code, http::StatusCode::FORBIDDEN);
+                assert_eq!(body, "Request has expired");
+            }
+            other => panic!("{:?}", other.map(|_| ())),
+        }
     }
 
     #[test]
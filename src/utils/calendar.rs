@@ -0,0 +1,219 @@
+//! Singapore public holidays and the day types that fares, timetables and passenger volumes
+//! are published for
+
+use std::collections::BTreeSet;
+use std::sync::{OnceLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+use time::macros::date;
+use time::{Date, Weekday};
+
+/// Public holidays gazetted by MOM, including the Mondays given when a holiday falls on
+/// a Sunday. Use [`add_holidays`] for years that are not listed or for holidays that are
+/// announced at short notice
+const SG_PUBLIC_HOLIDAYS: &[Date] = &[
+    // 2023
+    date!(2023 - 01 - 01),
+    date!(2023 - 01 - 02),
+    date!(2023 - 01 - 22),
+    date!(2023 - 01 - 23),
+    date!(2023 - 01 - 24),
+    date!(2023 - 04 - 07),
+    date!(2023 - 04 - 22),
+    date!(2023 - 05 - 01),
+    date!(2023 - 06 - 02),
+    date!(2023 - 06 - 29),
+    date!(2023 - 08 - 09),
+    date!(2023 - 09 - 01),
+    date!(2023 - 11 - 12),
+    date!(2023 - 11 - 13),
+    date!(2023 - 12 - 25),
+    // 2024
+    date!(2024 - 01 - 01),
+    date!(2024 - 02 - 10),
+    date!(2024 - 02 - 11),
+    date!(2024 - 02 - 12),
+    date!(2024 - 03 - 29),
+    date!(2024 - 04 - 10),
+    date!(2024 - 05 - 01),
+    date!(2024 - 05 - 22),
+    date!(2024 - 06 - 17),
+    date!(2024 - 08 - 09),
+    date!(2024 - 10 - 31),
+    date!(2024 - 12 - 25),
+    // 2025
+    date!(2025 - 01 - 01),
+    date!(2025 - 01 - 29),
+    date!(2025 - 01 - 30),
+    date!(2025 - 03 - 31),
+    date!(2025 - 04 - 18),
+    date!(2025 - 05 - 01),
+    date!(2025 - 05 - 03),
+    date!(2025 - 05 - 12),
+    date!(2025 - 06 - 07),
+    date!(2025 - 08 - 09),
+    date!(2025 - 08 - 18),
+    date!(2025 - 10 - 20),
+    date!(2025 - 12 - 25),
+    // 2026
+    date!(2026 - 01 - 01),
+    date!(2026 - 02 - 17),
+    date!(2026 - 02 - 18),
+    date!(2026 - 03 - 21),
+    date!(2026 - 04 - 03),
+    date!(2026 - 05 - 01),
+    date!(2026 - 05 - 27),
+    date!(2026 - 05 - 31),
+    date!(2026 - 06 - 01),
+    date!(2026 - 08 - 09),
+    date!(2026 - 08 - 10),
+    date!(2026 - 11 - 08),
+    date!(2026 - 11 - 09),
+    date!(2026 - 12 - 25),
+];
+
+static GLOBAL: OnceLock<RwLock<HolidayCalendar>> = OnceLock::new();
+
+fn global() -> &'static RwLock<HolidayCalendar> {
+    GLOBAL.get_or_init(|| RwLock::new(HolidayCalendar::singapore()))
+}
+
+/// Adds holidays to the calendar used by [`DayType::for_date`]
+pub fn add_holidays(dates: impl IntoIterator<Item = Date>) {
+    global()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .extend(dates);
+}
+
+/// Returns a copy of the calendar used by [`DayType::for_date`]
+pub fn holidays() -> HolidayCalendar {
+    global().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Set of public holidays
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HolidayCalendar {
+    holidays: BTreeSet<Date>,
+}
+
+impl HolidayCalendar {
+    /// Calendar without any holidays
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calendar with the built-in Singapore public holidays
+    pub fn singapore() -> Self {
+        SG_PUBLIC_HOLIDAYS.iter().copied().collect()
+    }
+
+    pub fn add(&mut self, date: Date) {
+        self.holidays.insert(date);
+    }
+
+    pub fn remove(&mut self, date: Date) {
+        self.holidays.remove(&date);
+    }
+
+    pub fn is_holiday(&self, date: Date) -> bool {
+        self.holidays.contains(&date)
+    }
+
+    /// Holidays of a year, in order
+    pub fn holidays_in(&self, year: i32) -> impl Iterator<Item = Date> + '_ {
+        self.holidays
+            .iter()
+            .copied()
+            .filter(move |d| d.year() == year)
+    }
+
+    /// Day type of `date`. Holidays are treated as Sundays
+    pub fn day_type(&self, date: Date) -> DayType {
+        if self.is_holiday(date) {
+            DayType::SundayOrHoliday
+        } else {
+            DayType::for_weekday(date.weekday())
+        }
+    }
+}
+
+impl Extend<Date> for HolidayCalendar {
+    fn extend<T: IntoIterator<Item = Date>>(&mut self, iter: T) {
+        self.holidays.extend(iter);
+    }
+}
+
+impl FromIterator<Date> for HolidayCalendar {
+    fn from_iter<T: IntoIterator<Item = Date>>(iter: T) -> Self {
+        HolidayCalendar {
+            holidays: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Day types used by LTA
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DayType {
+    Weekday,
+    Saturday,
+    SundayOrHoliday,
+}
+
+impl DayType {
+    /// Day type of `date`, using the built-in Singapore public holidays and the ones added
+    /// with [`add_holidays`]
+    pub fn for_date(date: Date) -> Self {
+        global()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .day_type(date)
+    }
+
+    /// Day type of a day of the week that is not a public holiday
+    pub fn for_weekday(weekday: Weekday) -> Self {
+        match weekday {
+            Weekday::Saturday => DayType::Saturday,
+            Weekday::Sunday => DayType::SundayOrHoliday,
+            _ => DayType::Weekday,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_holidays() {
+        // Good Friday
+        assert_eq!(
+            DayType::for_date(date!(2024 - 03 - 29)),
+            DayType::SundayOrHoliday
+        );
+        // Monday after National Day on a Sunday
+        assert_eq!(
+            DayType::for_date(date!(2026 - 08 - 10)),
+            DayType::SundayOrHoliday
+        );
+        // Hari Raya Haji on a Saturday is not moved
+        assert_eq!(DayType::for_date(date!(2025 - 06 - 09)), DayType::Weekday);
+        assert_eq!(DayType::for_date(date!(2024 - 03 - 02)), DayType::Saturday);
+        assert_eq!(HolidayCalendar::singapore().holidays_in(2024).count(), 12);
+    }
+
+    #[test]
+    fn user_holidays() {
+        let mut calendar = HolidayCalendar::new();
+        assert_eq!(calendar.day_type(date!(2024 - 03 - 29)), DayType::Weekday);
+        calendar.add(date!(2024 - 03 - 29));
+        assert!(calendar.is_holiday(date!(2024 - 03 - 29)));
+
+        add_holidays([date!(2030 - 01 - 01)]);
+        assert!(holidays().is_holiday(date!(2030 - 01 - 01)));
+        assert_eq!(
+            DayType::for_date(date!(2030 - 01 - 01)),
+            DayType::SundayOrHoliday
+        );
+    }
+}
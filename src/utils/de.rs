@@ -0,0 +1,41 @@
+//! Deserializers for the files linked by the APIs, which are not consistent about quoting numbers
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StrOrNum<T> {
+    Num(T),
+    Str(String),
+}
+
+/// Accepts either a number or a string containing one
+pub(crate) fn num_or_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    match StrOrNum::<T>::deserialize(deserializer)? {
+        StrOrNum::Num(n) => Ok(n),
+        StrOrNum::Str(s) => s.trim().parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Same as `num_or_str`, with `null` and blank strings treated as `None`
+pub(crate) fn opt_num_or_str<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    match Option::<StrOrNum<T>>::deserialize(deserializer)? {
+        Some(StrOrNum::Num(n)) => Ok(Some(n)),
+        Some(StrOrNum::Str(s)) if s.trim().is_empty() => Ok(None),
+        Some(StrOrNum::Str(s)) => s.trim().parse().map(Some).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
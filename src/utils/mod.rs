@@ -0,0 +1,41 @@
+use time::{macros::offset, Date, OffsetDateTime, UtcOffset};
+
+pub mod calendar;
+pub(crate) mod de;
+
+pub use calendar::{DayType, HolidayCalendar};
+
+/// Singapore Standard Time (GMT+8). Singapore does not observe daylight saving time
+pub const SGT: UtcOffset = offset!(+8);
+
+/// Returns the current date in Singapore
+pub(crate) fn today_sgt() -> Date {
+    OffsetDateTime::now_utc().to_offset(SGT).date()
+}
+
+/// Source of the current time, so that time dependent helpers can be tested.
+/// An `OffsetDateTime` is a clock that is stopped at that time
+pub trait Clock {
+    fn now(&self) -> OffsetDateTime;
+
+    /// Current time in Singapore
+    fn now_sgt(&self) -> OffsetDateTime {
+        self.now().to_offset(SGT)
+    }
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+impl Clock for OffsetDateTime {
+    fn now(&self) -> OffsetDateTime {
+        *self
+    }
+}